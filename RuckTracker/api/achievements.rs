@@ -1,177 +1,666 @@
-use actix_web::{web, App, HttpServer, HttpResponse, Responder, Error as ActixError};
-use actix_web::dev::ServiceRequest;
-use actix_web::middleware::Logger;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use chrono::{DateTime, Utc, Duration, NaiveDate};
-use log::{info, error, warn};
+use actix_web::{web, App, HttpServer, HttpResponse};
+use actix_web::middleware::from_fn;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use chrono::{Utc, Duration};
+use tracing::{info, error, instrument, warn, Instrument};
 use uuid::Uuid;
-use reqwest::Client; // For Supabase API calls (assuming no official Rust client, use HTTP)
-use std::env;
-
-// Assuming a simple in-memory cache for this example; use Redis in production
-type Cache = Arc<Mutex<HashMap<String, (serde_json::Value, DateTime<Utc>)>>>;
-
-// Supabase config
-struct SupabaseConfig {
-    url: String,
-    anon_key: String,
-    admin_key: String, // For admin operations
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-struct Achievement {
-    id: i32,
-    achievement_key: String,
-    name: String,
-    description: String,
-    category: String,
-    tier: String,
-    criteria: HashMap<String, serde_json::Value>,
-    icon_name: Option<String>,
-    is_active: bool,
-    created_at: Option<String>,
-    updated_at: Option<String>,
-    unit_preference: Option<String>,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-struct UserAchievement {
-    earned_at: String,
-    metadata: HashMap<String, serde_json::Value>,
-    user_id: Uuid,
-    achievement: Achievement,
-}
 
-// Helper functions (deduplicated)
+use ruck_api::{admin, health, metrics, supabase};
+use ruck_api::auth::{caller_auth, jwt_auth, AdminUsers, AuthenticatedUser, JwtVerifier};
+use ruck_api::cache::{cache_from_config, cache_get, cache_set, Cache};
+use ruck_api::config::ServerConfig;
+use ruck_api::criteria::{distance_km_from_name, Criteria};
+use ruck_api::error::{AppError, UpstreamContext};
+use ruck_api::evaluation::{check_criteria, explain_criteria, meets_minimum_session, session_f64, SessionMinimums};
+use ruck_api::metrics::{observe_award, observe_session_below_minimum, track_requests};
+use ruck_api::models::{parse_achievements, Achievement};
+use ruck_api::notifications::Notifier;
+use ruck_api::power_points::total_power_points;
+use ruck_api::progress::update_achievement_progress;
+use ruck_api::revocation::reevaluate_session_awards;
+use ruck_api::stats::UserStats;
+use ruck_api::streaks::load_user_streaks;
+use ruck_api::supabase::{Auth, Query, QueryResponse, SupabaseConfig};
+use ruck_api::telemetry::{init_tracing, request_tracing};
 
-// Get Supabase client (HTTP)
-async fn get_supabase_client(config: &SupabaseConfig, is_admin: bool) -> Client {
-    let client = Client::new();
-    client // In real, add auth headers with anon_key or admin_key
-}
+// Helper functions (deduplicated)
 
-// Cache get (deduplicated)
-async fn cache_get(cache: &Cache, key: &str) -> Option<serde_json::Value> {
-    let cache = cache.lock().unwrap();
-    if let Some((value, expiry)) = cache.get(key) {
-        if Utc::now() < *expiry {
-            return Some(value.clone());
+// Load every achievement definition once at startup and log each row whose criteria won't parse
+async fn validate_achievement_definitions(config: &SupabaseConfig) {
+    match Query::table("achievements").select("*").admin().execute(config).await {
+        Ok(response) => {
+            let (achievements, errors) = parse_achievements(response.rows());
+            for e in &errors {
+                error!("{}", e);
+            }
+            // Distance bands used to come from badge names; evaluation still falls back to the name
+            // for rows that haven't been migrated, but flag them so the band is made explicit
+            for achievement in &achievements {
+                if let Criteria::PaceFasterThan { min_distance_km: None, max_distance_km: None, .. } = achievement.criteria {
+                    if distance_km_from_name(&achievement.name).is_some() {
+                        warn!(
+                            "Achievement {} has no distance band; using the one its name implies until migrate_pace_criteria runs",
+                            achievement.achievement_key
+                        );
+                    }
+                }
+            }
+            info!(
+                "Achievement definitions loaded: valid={}, invalid={}",
+                achievements.len(),
+                errors.len()
+            );
         }
+        Err(e) => warn!("Could not validate achievement definitions at startup: {}", e),
     }
-    None
 }
 
-// Cache set (deduplicated)
-fn cache_set(cache: &Cache, key: String, value: serde_json::Value, ttl_seconds: i64) {
-    let mut cache = cache.lock().unwrap();
-    cache.insert(key, (value, Utc::now() + Duration::seconds(ttl_seconds)));
+// The session row a lookup by id returned, with its owner: 404 when RLS or a bad id hid it
+fn session_from_response(
+    response: &QueryResponse,
+    session_id: i64,
+) -> Result<(HashMap<String, serde_json::Value>, Uuid), AppError> {
+    let row = match response.first() {
+        Some(row) => row,
+        None => {
+            warn!("Session {} not found", session_id);
+            return Err(AppError::NotFound("Session not found"));
+        }
+    };
+    let session: HashMap<String, serde_json::Value> =
+        serde_json::from_value(row.clone()).map_err(|e| AppError::decode("session", row, e))?;
+    let user_id = session
+        .get("user_id")
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .ok_or_else(|| AppError::decode("session", row, "missing or invalid user_id"))?;
+    Ok((session, user_id))
 }
 
-// Execute Supabase query (deduplicated for all GET/POST)
-async fn execute_supabase_query(
-    config: &SupabaseConfig,
-    table: &str,
-    method: &str,
-    params: Option<HashMap<String, serde_json::Value>>,
-    is_admin: bool,
-) -> Result<serde_json::Value, ActixError> {
-    let client = get_supabase_client(config, is_admin).await;
-    let url = format!("{}/rest/v1/{}", config.url, table);
-    // Implement POST/GET with params, handle errors
-    // For simplicity, assume GET
-    let resp = client.get(&url).send().await.map_err(|e| {
-        error!("Supabase query error: {}", e);
-        ActixError::from(e)
-    })?;
-    resp.json().await.map_err(|e| {
-        error!("JSON parse error: {}", e);
-        ActixError::from(e)
-    })
+fn parse_user_id(raw: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(raw).map_err(|_| AppError::Validation("Invalid user id".to_string()))
 }
 
 // Handlers
 
+#[instrument(skip_all)]
 async fn achievements_handler(
     config: web::Data<SupabaseConfig>,
     cache: web::Data<Cache>,
+    settings: web::Data<ServerConfig>,
     query: web::Query<HashMap<String, String>>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let unit_preference = query.get("unit_preference").cloned().unwrap_or("metric".to_string());
     let cache_key = format!("achievements:all:{}", unit_preference);
 
     if let Some(cached) = cache_get(&cache, &cache_key).await {
-        return HttpResponse::Ok().json(json!({ "status": "success", "achievements": cached }));
+        return Ok(HttpResponse::Ok().json(json!({ "status": "success", "achievements": cached })));
     }
 
-    match execute_supabase_query(&config, "achievements", "GET", None, false).await {
-        Ok(data) => {
-            // Filter by unit_preference (deduplicated logic)
-            let filtered: Vec<Achievement> = data.as_array().unwrap().iter().filter_map(|item| {
-                let ach: Achievement = serde_json::from_value(item.clone()).ok()?;
-                if ach.unit_preference.is_none() || ach.unit_preference.as_ref() == Some(&unit_preference) {
-                    Some(ach)
-                } else {
-                    None
-                }
-            }).collect();
+    // Use admin key since achievements are public data; universal rows plus the requested unit
+    let mut query = Query::table("achievements")
+        .select("id, achievement_key, name, description, category, tier, criteria, icon_name, is_active, created_at, updated_at, unit_preference")
+        .eq("is_active", true)
+        .admin();
+    if unit_preference == "metric" || unit_preference == "standard" {
+        query = query.or(&format!("unit_preference.is.null,unit_preference.eq.{}", unit_preference));
+    }
+    let response = query.execute(&config).await.context("Failed to fetch achievements")?;
+    // Rows are returned and cached as stored, like the Python resource; criteria that won't parse
+    // are only logged here since evaluation skips them
+    let (_, errors) = parse_achievements(response.rows());
+    for e in &errors {
+        error!("{}", e);
+    }
+
+    // An empty read (e.g. mid-deploy or a policy mistake) is cached briefly, as Python did
+    let ttl = if response.rows().is_empty() { settings.achievements_empty_cache_ttl_s } else { settings.achievements_cache_ttl_s };
+    cache_set(&cache, &cache_key, &response.data, ttl).await;
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "achievements": response.data })))
+}
 
-            cache_set(&cache, cache_key, serde_json::to_value(&filtered).unwrap(), 1800);
-            HttpResponse::Ok().json(json!({ "status": "success", "achievements": filtered }))
+// Similarly implement other handlers, deduplicating query execution, caching, filtering, etc.
+
+// Get achievement categories
+#[instrument(skip_all)]
+async fn achievement_categories_handler(
+    config: web::Data<SupabaseConfig>,
+    user: Option<AuthenticatedUser>,
+) -> Result<HttpResponse, AppError> {
+    let response = Query::table("achievements")
+        .select("category")
+        .eq("is_active", true)
+        .auth(caller_auth(user.as_ref()))
+        .execute(&config)
+        .await
+        .context("Failed to fetch achievement categories")?;
+    let mut categories: Vec<&str> = Vec::new();
+    for category in response.rows().iter().filter_map(|row| row.get("category").and_then(|v| v.as_str())) {
+        if !categories.contains(&category) {
+            categories.push(category);
         }
-        Err(e) => HttpResponse::InternalServerError().json(json!({ "error": "Failed to fetch achievements" })),
     }
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "categories": categories })))
 }
 
-// Similarly implement other handlers, deduplicating query execution, caching, filtering, etc.
+// Get user's earned achievements
+#[instrument(skip_all)]
+async fn user_achievements_handler(
+    config: web::Data<SupabaseConfig>,
+    user: Option<AuthenticatedUser>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = parse_user_id(&path.into_inner())?;
+    let response = Query::table("user_achievements")
+        .select(
+            "id, achievement_id, session_id, earned_at, progress_value, metadata, \
+             achievements(name, description, tier, category, icon_name, achievement_key)",
+        )
+        .eq("user_id", user_id)
+        .order("earned_at", true)
+        .auth(caller_auth(user.as_ref()))
+        .execute(&config)
+        .await
+        .context("Failed to fetch user achievements")?;
+    info!("Fetched achievements for user {}: count={}", user_id, response.rows().len());
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "user_achievements": response.rows() })))
+}
 
-// For CheckSessionAchievements - this is complex, deduplicate criteria checks into a function
+// Get user's progress toward unearned achievements
+#[instrument(skip_all)]
+async fn user_achievements_progress_handler(
+    config: web::Data<SupabaseConfig>,
+    user: Option<AuthenticatedUser>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = parse_user_id(&path.into_inner())?;
+    let response = Query::table("achievement_progress")
+        .select("*, achievements(name, tier, category, icon_name, achievement_key)")
+        .eq("user_id", user_id)
+        .auth(caller_auth(user.as_ref()))
+        .execute(&config)
+        .await
+        .context("Failed to fetch achievement progress")?;
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "achievement_progress": response.rows() })))
+}
 
-async fn check_criteria(
-    supabase: &Client, // Pass supabase client
+// Get a user's current and longest daily / weekly / weekend streaks in their local timezone
+#[instrument(skip_all)]
+async fn user_streaks_handler(
+    config: web::Data<SupabaseConfig>,
+    user: Option<AuthenticatedUser>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = parse_user_id(&path.into_inner())?;
+    let grace_days = query.get("grace_days").and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+    let streaks = load_user_streaks(&config, caller_auth(user.as_ref()), user_id, grace_days, Utc::now())
+        .await
+        .context("Failed to fetch streaks")?;
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "streaks": streaks })))
+}
+
+// Get achievement statistics for a user
+#[instrument(skip_all)]
+async fn achievement_stats_handler(
+    config: web::Data<SupabaseConfig>,
+    user: Option<AuthenticatedUser>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = parse_user_id(&path.into_inner())?;
+    let unit_preference = query.get("unit_preference").cloned().unwrap_or("metric".to_string());
+    let stats = achievement_stats(&config, caller_auth(user.as_ref()), user_id, &unit_preference)
+        .await
+        .context("Failed to fetch achievement stats")?;
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "stats": stats })))
+}
+
+async fn achievement_stats(
+    config: &SupabaseConfig,
+    auth: Auth,
     user_id: Uuid,
-    session: &HashMap<String, serde_json::Value>,
-    achievement: &Achievement,
-    user_stats: &HashMap<String, f64>,
-) -> bool {
-    let criteria = &achievement.criteria;
-    let criteria_type = criteria.get("type").and_then(|v| v.as_str()).unwrap_or("");
-
-    match criteria_type {
-        "first_ruck" => true,
-        "single_session_distance" => {
-            let target = criteria.get("target").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let distance = session.get("distance_km").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            distance >= target
+    unit_preference: &str,
+) -> Result<serde_json::Value, supabase::SupabaseError> {
+    let earned_response = Query::table("user_achievements")
+        .select("achievements(category, tier)")
+        .eq("user_id", user_id)
+        .auth(auth.clone())
+        .execute(config)
+        .await?;
+
+    // Count by category and tier
+    let mut category_counts: HashMap<String, u64> = HashMap::new();
+    let mut tier_counts: HashMap<String, u64> = HashMap::new();
+    let total_earned = earned_response.rows().len();
+    for item in earned_response.rows() {
+        let achievement = item.get("achievements");
+        if let Some(category) = achievement.and_then(|a| a.get("category")).and_then(|v| v.as_str()) {
+            *category_counts.entry(category.to_string()).or_insert(0) += 1;
+        }
+        if let Some(tier) = achievement.and_then(|a| a.get("tier")).and_then(|v| v.as_str()) {
+            *tier_counts.entry(tier.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    // Total available uses the admin key to bypass RLS, filtered by unit preference when valid
+    let mut total_query = Query::table("achievements").select("id").eq("is_active", true).admin();
+    if unit_preference == "metric" || unit_preference == "standard" {
+        total_query = total_query.or(&format!("unit_preference.is.null,unit_preference.eq.{}", unit_preference));
+    }
+    let total_available = total_query.execute(config).await?.rows().len();
+
+    let total_power_points = total_power_points(config, auth, user_id).await?;
+
+    let completion_percentage = if total_available > 0 {
+        (total_earned as f64 / total_available as f64 * 1000.0).round() / 10.0
+    } else {
+        0.0
+    };
+    Ok(json!({
+        "total_earned": total_earned,
+        "total_available": total_available,
+        "completion_percentage": completion_percentage,
+        "power_points": total_power_points.round() as i64,
+        "by_category": category_counts,
+        "by_tier": tier_counts
+    }))
+}
+
+// Get recently earned achievements across the platform
+#[instrument(skip_all)]
+async fn recent_achievements_handler(
+    config: web::Data<SupabaseConfig>,
+    cache: web::Data<Cache>,
+    settings: web::Data<ServerConfig>,
+    user: Option<AuthenticatedUser>,
+) -> Result<HttpResponse, AppError> {
+    let since = Utc::now() - Duration::days(7);
+    // Cache by date (YYYY-MM-DD) to ensure fresh data
+    let cache_key = format!("achievements:recent:{}", since.format("%Y-%m-%d"));
+
+    if let Some(cached) = cache_get(&cache, &cache_key).await {
+        return Ok(HttpResponse::Ok().json(json!({ "status": "success", "recent_achievements": cached })));
+    }
+
+    let response = Query::table("user_achievements")
+        .select("earned_at, metadata, user_id, achievements(name, description, tier, category, icon_name, achievement_key)")
+        .gte("earned_at", since.format("%Y-%m-%dT%H:%M:%S%.6f"))
+        .order("earned_at", true)
+        .limit(50)
+        .auth(caller_auth(user.as_ref()))
+        .execute(&config)
+        .await
+        .context("Failed to fetch recent achievements")?;
+    // Recent achievements change frequently; cache empty results for less time
+    let ttl = if response.rows().is_empty() { settings.recent_empty_cache_ttl_s } else { settings.recent_cache_ttl_s };
+    cache_set(&cache, &cache_key, &response.data, ttl).await;
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "recent_achievements": response.rows() })))
+}
+
+// Check and award achievements for a completed session
+#[instrument(skip_all)]
+async fn check_session_achievements_handler(
+    config: web::Data<SupabaseConfig>,
+    settings: web::Data<ServerConfig>,
+    notifier: web::Data<Notifier>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let session_id = path.into_inner();
+    check_session_achievements(
+        &config,
+        &notifier,
+        user.supabase_auth(),
+        session_id,
+        settings.max_awards_per_session,
+        settings.session_minimums,
+    )
+        .await
+        .map_err(|e| e.upstream_message("Failed to check session achievements"))
+}
+
+// Session lookup and award inserts run as the caller so RLS applies; evaluation reads use the admin key.
+// `max_awards` caps what one session can earn, guarding against mass awarding.
+#[instrument(skip(config, notifier, auth))]
+async fn check_session_achievements(
+    config: &SupabaseConfig,
+    notifier: &web::Data<Notifier>,
+    auth: Auth,
+    session_id: i64,
+    max_awards: usize,
+    minimums: SessionMinimums,
+) -> Result<HttpResponse, AppError> {
+    info!("Achievement check called for session {}", session_id);
+
+    let session_response = Query::table("ruck_session")
+        .select("*")
+        .eq("id", session_id)
+        .auth(auth.clone())
+        .execute(config)
+        .await?;
+    let (session, user_id) = session_from_response(&session_response, session_id)?;
+
+    // Global min requirements
+    if !meets_minimum_session(&session, minimums) {
+        observe_session_below_minimum();
+        info!(
+            "Session {} below minimum requirements: duration={:?}s, distance={:?}km",
+            session_id,
+            session_f64(&session, "duration_seconds"),
+            session_f64(&session, "distance_km")
+        );
+        return Ok(HttpResponse::Ok().json(json!({
+            "status": "success",
+            "new_achievements": [],
+            "session_id": session_id,
+            "message": "Session does not meet minimum requirements for achievements"
+        })));
+    }
+
+    let user_response = Query::table("user").select("prefer_metric").eq("id", user_id).admin().execute(config).await?;
+    let prefer_metric = user_response
+        .first()
+        .and_then(|row| row.get("prefer_metric"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let unit_preference = if prefer_metric { "metric" } else { "standard" };
+
+    let existing_response = Query::table("user_achievements")
+        .select("achievement_id, session_id")
+        .eq("user_id", user_id)
+        .admin()
+        .execute(config)
+        .await?;
+    let existing_ids: HashSet<i64> = existing_response
+        .rows()
+        .iter()
+        .filter_map(|row| row.get("achievement_id").and_then(|v| v.as_i64()))
+        .collect();
+    // Awards an earlier call already made for this session, e.g. one whose response the client never got
+    let session_award_ids: HashSet<i64> = existing_response
+        .rows()
+        .iter()
+        .filter(|row| row.get("session_id").and_then(|v| v.as_i64()) == Some(session_id))
+        .filter_map(|row| row.get("achievement_id").and_then(|v| v.as_i64()))
+        .collect();
+
+    // Active achievements that are universal or match the user's unit preference
+    let achievements_response = Query::table("achievements")
+        .select("*")
+        .eq("is_active", true)
+        .or(&format!("unit_preference.is.null,unit_preference.eq.{}", unit_preference))
+        .admin()
+        .execute(config)
+        .await?;
+    let (all_achievements, errors) = parse_achievements(achievements_response.rows());
+    for e in &errors {
+        error!("{}", e);
+    }
+    let total_achievements = all_achievements.len();
+    let achievements: Vec<&Achievement> = all_achievements
+        .iter()
+        .filter(|a| !existing_ids.contains(&i64::from(a.id)))
+        .collect();
+
+    let mut stats = UserStats::load(config, user_id).await?;
+    stats.load_splits(config, &[session_id]).await?;
+    info!(
+        "Achievement check: user={}, unit={}, total={}, already_earned={}, to_check={}, sessions={}",
+        user_id,
+        unit_preference,
+        total_achievements,
+        existing_ids.len(),
+        achievements.len(),
+        stats.sessions.len()
+    );
+
+    // The cap covers everything this session has earned, so retries can't push it past the limit
+    let award_limit = max_awards.saturating_sub(session_award_ids.len());
+    let mut new_achievements: Vec<Achievement> = Vec::new();
+    for achievement in achievements.iter().copied() {
+        if !check_criteria(&session, achievement, &stats, minimums) {
+            continue;
         }
-        // Deduplicate other criteria into match arms, reuse common logic like fetching stats
-        _ => false,
+        if new_achievements.len() >= award_limit {
+            warn!("Award cap reached ({}). Skipping {} for session {}", max_awards, achievement.achievement_key, session_id);
+            continue;
+        }
+
+        let award_data = json!({
+            "user_id": user_id,
+            "achievement_id": achievement.id,
+            "session_id": session_id,
+            "earned_at": Utc::now().to_rfc3339(),
+            "metadata": {
+                "triggered_by_session": session_id,
+                "unit_preference": unit_preference,
+                "session_distance_km": session.get("distance_km"),
+                "session_duration_s": session.get("duration_seconds"),
+            }
+        });
+
+        // Unique on (user_id, achievement_id): a concurrent check that got there first returns no row
+        match Query::table("user_achievements")
+            .insert_ignoring_duplicates(award_data)
+            .on_conflict("user_id,achievement_id")
+            .auth(auth.clone())
+            .execute(config)
+            .await
+        {
+            Ok(response) if response.rows().is_empty() => {
+                info!("{} already awarded to user {}, skipping", achievement.achievement_key, user_id);
+            }
+            Ok(_) => {
+                info!(achievement_key = %achievement.achievement_key, %user_id, unit_preference, "achievement awarded");
+                observe_award(&achievement.achievement_key);
+                new_achievements.push(achievement.clone());
+            }
+            Err(e) => error!("Failed to insert achievement {}: {}", achievement.achievement_key, e),
+        }
+    }
+
+    let still_unearned: Vec<&Achievement> = achievements
+        .iter()
+        .copied()
+        .filter(|a| !new_achievements.iter().any(|n| n.id == a.id))
+        .collect();
+    if let Err(e) = update_achievement_progress(config, user_id, &still_unearned, &stats).await {
+        error!("Failed to update achievement progress for user {}: {}", user_id, e);
     }
+
+    if !new_achievements.is_empty() {
+        let names: Vec<String> = new_achievements.iter().map(|a| a.name.clone()).collect();
+        let notifier = notifier.clone();
+        // Sent after responding so FCM latency doesn't hold up the check
+        actix_web::rt::spawn(
+            async move {
+                notifier.send_achievement_notification(user_id, &names, session_id).await;
+            }
+            .in_current_span(),
+        );
+    }
+    info!("Achievement check complete for session {}: {} new", session_id, new_achievements.len());
+
+    // Awards go back as the rows Python returned from select('*'), which the Flutter app parses
+    let new_rows: Vec<&serde_json::Value> = new_achievements
+        .iter()
+        .filter_map(|a| {
+            let id = i64::from(a.id);
+            achievements_response.rows().iter().find(|row| row.get("id").and_then(|v| v.as_i64()) == Some(id))
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({
+        "status": "success",
+        "new_achievements": new_rows,
+        "session_id": session_id
+    })))
+}
+
+// Re-check awards that depended on a session after it is edited or deleted; ruck.py calls this
+// from its session PATCH, edit and DELETE handlers
+#[instrument(skip_all)]
+async fn reevaluate_session_handler(
+    config: web::Data<SupabaseConfig>,
+    settings: web::Data<ServerConfig>,
+    user: AuthenticatedUser,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, AppError> {
+    let session_id = path.into_inner();
+    let revoked = reevaluate_session_awards(&config, user.supabase_auth(), user.user_id, session_id, settings.session_minimums)
+        .await
+        .map_err(|e| e.upstream_message("Failed to re-evaluate achievements"))?;
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "session_id": session_id, "revoked": revoked })))
 }
 
-// In the handler, use this function for each achievement
+// Explain why an achievement would or would not be awarded for a session
+#[instrument(skip_all)]
+async fn explain_achievement_handler(
+    config: web::Data<SupabaseConfig>,
+    settings: web::Data<ServerConfig>,
+    user: AuthenticatedUser,
+    path: web::Path<(i64, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (session_id, achievement_key) = path.into_inner();
+    explain_achievement(&config, user.supabase_auth(), session_id, &achievement_key, settings.session_minimums)
+        .await
+        .map_err(|e| e.upstream_message("Failed to explain achievement"))
+}
+
+// Runs the same evaluation as check_session_achievements in trace mode, without awarding anything
+#[instrument(skip(config, auth))]
+async fn explain_achievement(
+    config: &SupabaseConfig,
+    auth: Auth,
+    session_id: i64,
+    achievement_key: &str,
+    minimums: SessionMinimums,
+) -> Result<HttpResponse, AppError> {
+    let session_response = Query::table("ruck_session").select("*").eq("id", session_id).auth(auth).execute(config).await?;
+    let (session, user_id) = session_from_response(&session_response, session_id)?;
+
+    let achievement_response = Query::table("achievements")
+        .select("*")
+        .eq("achievement_key", achievement_key)
+        .admin()
+        .execute(config)
+        .await?;
+    let (achievements, errors) = parse_achievements(achievement_response.rows());
+    if let Some(e) = errors.first() {
+        error!("{}", e);
+        return Err(AppError::Unprocessable(e.to_string()));
+    }
+    let achievement = match achievements.into_iter().next() {
+        Some(achievement) => achievement,
+        None => return Err(AppError::NotFound("Achievement not found")),
+    };
+
+    let already_earned = !Query::table("user_achievements")
+        .select("id")
+        .eq("user_id", user_id)
+        .eq("achievement_id", achievement.id)
+        .admin()
+        .execute(config)
+        .await?
+        .rows()
+        .is_empty();
+
+    let meets_minimum = meets_minimum_session(&session, minimums);
+    let mut stats = UserStats::load(config, user_id).await?;
+    stats.load_splits(config, &[session_id]).await?;
+    let explanation = explain_criteria(&session, &achievement, &stats, minimums, true);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "status": "success",
+        "session_id": session_id,
+        "achievement_key": achievement.achievement_key,
+        "unit_preference": achievement.unit_preference,
+        "is_active": achievement.is_active,
+        "already_earned": already_earned,
+        "meets_minimum_session": meets_minimum,
+        "criteria_met": explanation.passed,
+        "would_award": achievement.is_active && !already_earned && meets_minimum && explanation.passed,
+        "explanation": explanation,
+    })))
+}
 
 // Server setup
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    env_logger::init();
-    let config = SupabaseConfig {
-        url: env::var("SUPABASE_URL").unwrap(),
-        anon_key: env::var("SUPABASE_ANON_KEY").unwrap(),
-        admin_key: env::var("SUPABASE_ADMIN_KEY").unwrap(),
+    init_tracing();
+    let settings = match ServerConfig::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("{}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
     };
-    let cache = web::Data::new(Arc::new(Mutex::new(HashMap::new())));
+    let config = web::Data::new(settings.supabase.clone());
+    validate_achievement_definitions(&config).await;
+    // HS256 projects set SUPABASE_JWT_SECRET; asymmetric signing keys are read from the project's JWKS
+    let verifier = web::Data::new(JwtVerifier::new(
+        settings.jwt_secret.clone(),
+        format!("{}/auth/v1/.well-known/jwks.json", config.url),
+    ));
+    let cache: web::Data<Cache> = match cache_from_config(&settings).await {
+        Ok(cache) => web::Data::new(cache),
+        Err(e) => {
+            error!("CACHE_BACKEND=redis but Redis is unavailable: {}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    let admin_users = web::Data::new(AdminUsers::new(&settings.admin_users));
+    let notifier = match Notifier::new(&settings, cache.get_ref().clone()) {
+        Ok(notifier) => web::Data::new(notifier),
+        Err(e) => {
+            error!("{}", e);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+        }
+    };
+    let (host, port, workers, shutdown_timeout_s) =
+        (settings.host.clone(), settings.port, settings.workers, settings.shutdown_timeout_s);
+    let settings = web::Data::new(settings);
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         App::new()
-            .wrap(Logger::default())
-            .app_data(web::Data::new(config.clone()))
+            .wrap(from_fn(jwt_auth))
+            .wrap(from_fn(track_requests))
+            .wrap(from_fn(request_tracing))
+            .app_data(verifier.clone())
+            .app_data(config.clone())
+            .app_data(settings.clone())
             .app_data(cache.clone())
+            .app_data(admin_users.clone())
+            .app_data(notifier.clone())
+            .route("/health", web::get().to(health::health_handler))
+            .route("/ready", web::get().to(health::ready_handler))
+            .route("/metrics", web::get().to(metrics::metrics_handler))
             .route("/achievements", web::get().to(achievements_handler))
+            .route("/achievements/categories", web::get().to(achievement_categories_handler))
+            .route("/achievements/recent", web::get().to(recent_achievements_handler))
+            .route("/achievements/stats/{user_id}", web::get().to(achievement_stats_handler))
+            .route("/achievements/check/{session_id}", web::post().to(check_session_achievements_handler))
+            .route("/achievements/reevaluate/{session_id}", web::post().to(reevaluate_session_handler))
+            .route("/achievements/explain/{session_id}/{achievement_key}", web::get().to(explain_achievement_handler))
+            .route("/users/{user_id}/achievements", web::get().to(user_achievements_handler))
+            .route("/users/{user_id}/achievements/progress", web::get().to(user_achievements_progress_handler))
+            .route("/users/{user_id}/streaks", web::get().to(user_streaks_handler))
+            .route("/admin/achievements", web::post().to(admin::create_achievement_handler))
+            .route("/admin/achievements/{id}", web::patch().to(admin::update_achievement_handler))
+            .route("/admin/achievements/{id}/deactivate", web::post().to(admin::deactivate_achievement_handler))
+            .route("/admin/achievements/{id}/clone", web::post().to(admin::clone_achievement_handler))
+            .route("/admin/cache", web::get().to(admin::cache_stats_handler))
+            .route("/admin/cache", web::delete().to(admin::purge_cache_handler))
             // Add other routes similarly
     })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    // On SIGTERM/SIGINT actix stops accepting connections and gives in-flight requests this long
+    .shutdown_timeout(shutdown_timeout_s);
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+    info!("Listening on {}:{}", host, port);
+    server.bind((host.as_str(), port))?.run().await?;
+    info!("Server stopped");
+    Ok(())
 } 
\ No newline at end of file
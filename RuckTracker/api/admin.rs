@@ -0,0 +1,247 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use tracing::{error, info, instrument};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use crate::auth::AdminUser;
+use crate::cache::Cache;
+use crate::criteria::Criteria;
+use crate::error::AppError;
+use crate::models::{parse_achievements, Achievement};
+use crate::supabase::{Query, SupabaseConfig};
+
+// Cache keys read by achievements_handler, one per unit_preference
+const ACHIEVEMENTS_CACHE_PATTERN: &str = "achievements:all:*";
+
+// Columns a create must set. icon_name, is_active and unit_preference are optional; id and the
+// timestamps are managed here and by the database, so validate_fields rejects them.
+const REQUIRED_FIELDS: [&str; 6] = ["achievement_key", "name", "description", "category", "tier", "criteria"];
+
+// Check a create (`partial` false) or update body field by field and return the row to write,
+// with criteria normalised through the typed schema. Errors are messages for a 400.
+fn validate_fields(body: &Value, partial: bool) -> Result<Map<String, Value>, String> {
+    let body = body.as_object().ok_or("Request body must be a JSON object")?;
+    if !partial {
+        if let Some(missing) = REQUIRED_FIELDS.iter().find(|f| !body.contains_key(**f)) {
+            return Err(format!("Missing required field '{}'", missing));
+        }
+    }
+
+    let key = body.get("achievement_key").and_then(|v| v.as_str());
+    let mut row = Map::new();
+    for (field, value) in body {
+        let value = match field.as_str() {
+            "achievement_key" => {
+                let key = value.as_str().unwrap_or("");
+                if key.is_empty() || !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+                    return Err("achievement_key must be non-empty lowercase letters, digits and underscores".to_string());
+                }
+                value.clone()
+            }
+            "name" | "description" | "category" | "tier" => match value.as_str() {
+                Some(text) if !text.trim().is_empty() => Value::String(text.trim().to_string()),
+                _ => return Err(format!("{} must be a non-empty string", field)),
+            },
+            "criteria" => {
+                let criteria = Criteria::from_value(key.unwrap_or("<new>"), value).map_err(|e| e.message)?;
+                serde_json::to_value(&criteria).map_err(|e| e.to_string())?
+            }
+            "icon_name" => match value {
+                Value::Null | Value::String(_) => value.clone(),
+                _ => return Err("icon_name must be a string or null".to_string()),
+            },
+            "is_active" => match value {
+                Value::Bool(_) => value.clone(),
+                _ => return Err("is_active must be a boolean".to_string()),
+            },
+            "unit_preference" => match value.as_str() {
+                None if value.is_null() => value.clone(),
+                Some("metric") | Some("standard") => value.clone(),
+                _ => return Err("unit_preference must be 'metric', 'standard' or null".to_string()),
+            },
+            _ => return Err(format!("Unknown or read-only field '{}'", field)),
+        };
+        row.insert(field.clone(), value);
+    }
+    if row.is_empty() {
+        return Err("No fields to update".to_string());
+    }
+    Ok(row)
+}
+
+async fn fetch_achievement(config: &SupabaseConfig, id: i32) -> Result<Option<Achievement>, AppError> {
+    let response = Query::table("achievements").select("*").eq("id", id).admin().execute(config).await?;
+    let (mut achievements, errors) = parse_achievements(response.rows());
+    for e in &errors {
+        error!("{}", e);
+    }
+    Ok(achievements.pop())
+}
+
+// Whether another row already uses `achievement_key`
+async fn key_taken(config: &SupabaseConfig, achievement_key: &str, except_id: Option<i32>) -> Result<bool, AppError> {
+    let mut query = Query::table("achievements").select("id").eq("achievement_key", achievement_key);
+    if let Some(id) = except_id {
+        query = query.neq("id", id);
+    }
+    Ok(!query.admin().execute(config).await?.rows().is_empty())
+}
+
+fn duplicate_key(achievement_key: &str) -> AppError {
+    AppError::Conflict(format!("Achievement key '{}' already exists", achievement_key))
+}
+
+async fn insert_achievement(config: &SupabaseConfig, cache: &Cache, row: Map<String, Value>) -> Result<HttpResponse, AppError> {
+    let response = Query::table("achievements").insert(Value::Object(row)).admin().execute(config).await?;
+    let removed = cache.delete_pattern(ACHIEVEMENTS_CACHE_PATTERN).await;
+    info!("Invalidated {} cached achievement lists", removed);
+    Ok(HttpResponse::Created().json(json!({ "status": "success", "achievement": response.first() })))
+}
+
+async fn update_achievement(
+    config: &SupabaseConfig,
+    cache: &Cache,
+    id: i32,
+    mut row: Map<String, Value>,
+) -> Result<HttpResponse, AppError> {
+    row.insert("updated_at".to_string(), json!(Utc::now().to_rfc3339()));
+    let response = Query::table("achievements").eq("id", id).update(Value::Object(row)).admin().execute(config).await?;
+    let removed = cache.delete_pattern(ACHIEVEMENTS_CACHE_PATTERN).await;
+    info!("Invalidated {} cached achievement lists", removed);
+    match response.first() {
+        Some(achievement) => Ok(HttpResponse::Ok().json(json!({ "status": "success", "achievement": achievement }))),
+        None => Err(AppError::NotFound("Achievement not found")),
+    }
+}
+
+// Create an achievement definition
+#[instrument(skip_all)]
+pub async fn create_achievement_handler(
+    config: web::Data<SupabaseConfig>,
+    cache: web::Data<Cache>,
+    admin: AdminUser,
+    body: web::Json<Value>,
+) -> Result<HttpResponse, AppError> {
+    let row = validate_fields(&body, false).map_err(AppError::Validation)?;
+    let key = row["achievement_key"].as_str().unwrap_or_default().to_string();
+    if key_taken(&config, &key, None).await? {
+        return Err(duplicate_key(&key));
+    }
+    info!("Admin {} creating achievement {}", admin.0.user_id, key);
+    insert_achievement(&config, &cache, row).await
+}
+
+// Update some or all fields of an achievement definition
+#[instrument(skip_all)]
+pub async fn update_achievement_handler(
+    config: web::Data<SupabaseConfig>,
+    cache: web::Data<Cache>,
+    admin: AdminUser,
+    path: web::Path<i32>,
+    body: web::Json<Value>,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let row = validate_fields(&body, true).map_err(AppError::Validation)?;
+    if let Some(key) = row.get("achievement_key").and_then(|v| v.as_str()) {
+        if key_taken(&config, key, Some(id)).await? {
+            return Err(duplicate_key(key));
+        }
+    }
+    info!("Admin {} updating achievement {}: {:?}", admin.0.user_id, id, row.keys().collect::<Vec<_>>());
+    update_achievement(&config, &cache, id, row).await
+}
+
+// Deactivate an achievement; rows are kept so existing awards still resolve
+#[instrument(skip_all)]
+pub async fn deactivate_achievement_handler(
+    config: web::Data<SupabaseConfig>,
+    cache: web::Data<Cache>,
+    admin: AdminUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    info!("Admin {} deactivating achievement {}", admin.0.user_id, id);
+    let mut row = Map::new();
+    row.insert("is_active".to_string(), json!(false));
+    update_achievement(&config, &cache, id, row).await
+}
+
+// Copy a metric achievement as a standard one or vice versa, converting unit-dependent targets.
+// The copy is inactive unless the body says otherwise, since its name and description usually
+// mention units and need editing first. Body fields override the copied ones.
+#[instrument(skip_all)]
+pub async fn clone_achievement_handler(
+    config: web::Data<SupabaseConfig>,
+    cache: web::Data<Cache>,
+    admin: AdminUser,
+    path: web::Path<i32>,
+    body: Option<web::Json<Value>>,
+) -> Result<HttpResponse, AppError> {
+    let id = path.into_inner();
+    let source = match fetch_achievement(&config, id).await? {
+        Some(achievement) => achievement,
+        None => return Err(AppError::NotFound("Achievement not found")),
+    };
+    let target_unit = match source.unit_preference.as_deref() {
+        Some("metric") => "standard",
+        Some("standard") => "metric",
+        _ => {
+            return Err(AppError::Validation(
+                "Only metric or standard achievements can be cloned to the other unit".to_string(),
+            ))
+        }
+    };
+
+    let mut fields = json!({
+        "achievement_key": format!("{}_{}", source.achievement_key, target_unit),
+        "name": source.name,
+        "description": source.description,
+        "category": source.category,
+        "tier": source.tier,
+        "criteria": source.criteria.converted_to(target_unit == "standard"),
+        "icon_name": source.icon_name,
+        "is_active": false,
+        "unit_preference": target_unit,
+    });
+    if let Some(overrides) = body.as_ref().and_then(|b| b.as_object()) {
+        for (field, value) in overrides {
+            if field == "unit_preference" {
+                return Err(AppError::Validation("unit_preference of a clone is always the other unit".to_string()));
+            }
+            fields[field.as_str()] = value.clone();
+        }
+    }
+    let row = validate_fields(&fields, false).map_err(AppError::Validation)?;
+    let key = row["achievement_key"].as_str().unwrap_or_default().to_string();
+    if key_taken(&config, &key, None).await? {
+        return Err(duplicate_key(&key));
+    }
+    info!(
+        "Admin {} cloning achievement {} as {} ({})",
+        admin.0.user_id, source.achievement_key, key, target_unit
+    );
+    insert_achievement(&config, &cache, row).await
+}
+
+// Entry count, hit/miss ratio and memory estimate of the active cache backend
+#[instrument(skip_all)]
+pub async fn cache_stats_handler(cache: web::Data<Cache>, _admin: AdminUser) -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "success", "cache": cache.stats().await }))
+}
+
+// Delete every cache key matching `?pattern=` (glob syntax, e.g. `achievements:*`)
+#[instrument(skip_all)]
+pub async fn purge_cache_handler(
+    cache: web::Data<Cache>,
+    admin: AdminUser,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let pattern = match query.get("pattern").map(|p| p.trim()) {
+        Some(pattern) if !pattern.is_empty() => pattern,
+        _ => return Err(AppError::Validation("Query parameter 'pattern' is required".to_string())),
+    };
+    let deleted = cache.delete_pattern(pattern).await;
+    info!("Admin {} purged {} cache keys matching {}", admin.0.user_id, deleted, pattern);
+    Ok(HttpResponse::Ok().json(json!({ "status": "success", "pattern": pattern, "deleted": deleted })))
+}
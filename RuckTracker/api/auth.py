@@ -3,6 +3,7 @@ from flask_restful import Resource
 from flask import Blueprint, make_response
 import uuid
 from datetime import datetime, timedelta
+from zoneinfo import ZoneInfo, ZoneInfoNotFoundError
 import sys
 import logging
 from functools import wraps
@@ -543,7 +544,7 @@ class UserProfileResource(Resource):
                 'username', 'weight_kg', 'prefer_metric', 'height_cm',
                 'allow_ruck_sharing', 'gender', 'date_of_birth', 'avatar_url',
                 'notification_clubs', 'notification_buddies', 'notification_events', 'notification_duels', 'notification_first_ruck',
-                'resting_hr', 'max_hr', 'calorie_method', 'calorie_active_only'
+                'resting_hr', 'max_hr', 'calorie_method', 'calorie_active_only', 'timezone'
             ]
             for field in allowed_fields:
                 if field == 'prefer_metric': # Check for snake_case field name
@@ -599,6 +600,16 @@ class UserProfileResource(Resource):
                             logger.warning(f"Invalid calorie_method '{method_val}' provided for user {g.user.id}")
                             return {'message': "Invalid calorie_method. Allowed: 'fusion', 'mechanical', 'hr'"}, 400
                         update_data['calorie_method'] = method_val
+                # IANA timezone reported by the app; streaks and time-of-day achievements use it
+                elif field == 'timezone':
+                    tz_val = data.get('timezone')
+                    if tz_val is not None:
+                        try:
+                            ZoneInfo(tz_val)
+                        except (ZoneInfoNotFoundError, ValueError, TypeError):
+                            logger.warning(f"Invalid timezone '{tz_val}' provided for user {g.user.id}")
+                            return {'message': 'Invalid timezone. Expected an IANA name such as America/Denver'}, 400
+                        update_data['timezone'] = tz_val
                 # Handle notification preferences (all expect snake_case keys)
                 elif field in ['notification_clubs', 'notification_buddies', 'notification_events', 'notification_duels']:
                     if field in data:
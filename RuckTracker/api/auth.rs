@@ -0,0 +1,236 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixError, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, errors::ErrorKind, Algorithm, DecodingKey, Validation};
+use tracing::{error, field, info, Span};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::{ready, Ready};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::supabase::{http_client, Auth};
+
+// Don't hammer the JWKS endpoint when tokens carry an unknown kid
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    ExpiredToken,
+    KeysUnavailable,
+    Forbidden,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            AuthError::MissingToken => "Authentication required",
+            AuthError::InvalidToken => "Invalid token",
+            AuthError::ExpiredToken => "Token has expired",
+            AuthError::KeysUnavailable => "Authentication service unavailable",
+            AuthError::Forbidden => "Admin privileges required",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::KeysUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            AuthError::Forbidden => StatusCode::FORBIDDEN,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({ "error": self.to_string() }))
+    }
+}
+
+// Supabase access token claims we rely on
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+// The verified caller, stored in request extensions by `jwt_auth`
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser {
+    pub user_id: Uuid,
+    pub token: String,
+}
+
+impl AuthenticatedUser {
+    // Forward the caller's JWT to PostgREST so row-level security applies (Python's g.access_token)
+    pub fn supabase_auth(&self) -> Auth {
+        Auth::User(self.token.clone())
+    }
+}
+
+// Caller's JWT when present, anon key otherwise (Python's get_supabase_client(user_jwt=...))
+pub fn caller_auth(user: Option<&AuthenticatedUser>) -> Auth {
+    user.map_or(Auth::Anon, AuthenticatedUser::supabase_auth)
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<AuthenticatedUser>().cloned().ok_or(AuthError::MissingToken))
+    }
+}
+
+// User ids allowed to call admin endpoints, from the comma-separated ADMIN_USERS (auth_helper.py)
+#[derive(Clone, Debug, Default)]
+pub struct AdminUsers(HashSet<Uuid>);
+
+impl AdminUsers {
+    pub fn new(list: &str) -> AdminUsers {
+        AdminUsers(list.split(',').filter_map(|id| Uuid::parse_str(id.trim()).ok()).collect())
+    }
+
+    pub fn contains(&self, user_id: Uuid) -> bool {
+        self.0.contains(&user_id)
+    }
+}
+
+// An authenticated caller listed in ADMIN_USERS; 401 without a token, 403 for everyone else
+#[derive(Clone, Debug)]
+pub struct AdminUser(pub AuthenticatedUser);
+
+impl FromRequest for AdminUser {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user = match req.extensions().get::<AuthenticatedUser>().cloned() {
+            Some(user) => user,
+            None => return ready(Err(AuthError::MissingToken)),
+        };
+        let is_admin = req.app_data::<web::Data<AdminUsers>>().is_some_and(|admins| admins.contains(user.user_id));
+        if !is_admin {
+            info!("Rejected non-admin user {} on {}", user.user_id, req.path());
+            return ready(Err(AuthError::Forbidden));
+        }
+        ready(Ok(AdminUser(user)))
+    }
+}
+
+#[derive(Default)]
+struct JwksCache {
+    keys: HashMap<String, DecodingKey>,
+    fetched_at: Option<Instant>,
+}
+
+// Validates Supabase-issued JWTs locally: HS256 with the project secret, RS256/ES256 via JWKS
+pub struct JwtVerifier {
+    hs256_key: Option<DecodingKey>,
+    jwks_url: String,
+    jwks: RwLock<JwksCache>,
+}
+
+impl JwtVerifier {
+    pub fn new(jwt_secret: Option<String>, jwks_url: String) -> JwtVerifier {
+        JwtVerifier {
+            hs256_key: jwt_secret.map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            jwks_url,
+            jwks: RwLock::new(JwksCache::default()),
+        }
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<AuthenticatedUser, AuthError> {
+        let header = decode_header(token).map_err(|_| AuthError::InvalidToken)?;
+        let key = match header.alg {
+            Algorithm::HS256 => self.hs256_key.clone().ok_or(AuthError::InvalidToken)?,
+            Algorithm::RS256 | Algorithm::ES256 => self.jwks_key(header.kid.as_deref()).await?,
+            _ => return Err(AuthError::InvalidToken),
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&["authenticated"]);
+        let data = decode::<Claims>(token, &key, &validation).map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+            _ => AuthError::InvalidToken,
+        })?;
+
+        let user_id = Uuid::parse_str(&data.claims.sub).map_err(|_| AuthError::InvalidToken)?;
+        Ok(AuthenticatedUser {
+            user_id,
+            token: token.to_string(),
+        })
+    }
+
+    async fn jwks_key(&self, kid: Option<&str>) -> Result<DecodingKey, AuthError> {
+        let kid = kid.ok_or(AuthError::InvalidToken)?;
+        {
+            let cache = self.jwks.read().unwrap();
+            if let Some(key) = cache.keys.get(kid) {
+                return Ok(key.clone());
+            }
+            if cache.fetched_at.is_some_and(|at| at.elapsed() < JWKS_REFRESH_INTERVAL) {
+                return Err(AuthError::InvalidToken);
+            }
+        }
+
+        let jwks: JwkSet = async {
+            http_client().get(&self.jwks_url).send().await?.error_for_status()?.json().await
+        }
+        .await
+        .map_err(|e: reqwest::Error| {
+            error!("Failed to fetch JWKS from {}: {}", self.jwks_url, e);
+            AuthError::KeysUnavailable
+        })?;
+        let keys: HashMap<String, DecodingKey> = jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| Some((jwk.common.key_id.clone()?, DecodingKey::from_jwk(jwk).ok()?)))
+            .collect();
+        info!("Loaded {} signing keys from JWKS", keys.len());
+
+        let mut cache = self.jwks.write().unwrap();
+        cache.keys = keys;
+        cache.fetched_at = Some(Instant::now());
+        cache.keys.get(kid).cloned().ok_or(AuthError::InvalidToken)
+    }
+}
+
+// Verify the bearer token if present; handlers that need a caller extract `AuthenticatedUser`
+pub async fn jwt_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixError> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string());
+
+    if let Some(token) = token {
+        let verifier = req
+            .app_data::<web::Data<JwtVerifier>>()
+            .expect("JwtVerifier must be registered as app data");
+        match verifier.verify(&token).await {
+            Ok(user) => {
+                Span::current().record("user_id", field::display(user.user_id));
+                req.extensions_mut().insert(user);
+            }
+            Err(e) => {
+                info!("Rejected bearer token on {}: {}", req.path(), e);
+                return Err(e.into());
+            }
+        }
+    }
+
+    next.call(req).await
+}
@@ -0,0 +1,517 @@
+// Retroactive achievement backfill: replays each user's completed sessions oldest-first through
+// the same `check_criteria` the API uses and reconciles user_achievements with the result.
+//
+//   backfill_achievements --dry-run            print the diff for every user, change nothing
+//   backfill_achievements --user <uuid>        limit to one user
+//   backfill_achievements --revoke             also rewrite and delete existing awards
+//
+// Diff lines: "+" award to add, "~" award whose session_id/earned_at gets corrected,
+// "-" award to revoke (duplicates included). Without --revoke only "+" lines are written.
+// Revocations go through revoke_user_achievement, so each leaves a row in achievement_revocations,
+// and corrections keep the replaced session_id/earned_at in the award's metadata.
+// Achievements that are inactive or for the other unit system are never touched, and a session
+// earns at most MAX_AWARDS_PER_SESSION awards, as in the live check. Failed writes exit non-zero.
+use chrono::Utc;
+use tracing::{error, info, warn};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use uuid::Uuid;
+
+use ruck_api::config::ServerConfig;
+use ruck_api::evaluation::{check_criteria, evaluation_time, meets_minimum_session, parse_timestamp, SessionMinimums};
+use ruck_api::models::{parse_achievements, Achievement};
+use ruck_api::stats::UserStats;
+use ruck_api::supabase::{Query, SupabaseConfig, SupabaseError};
+use ruck_api::telemetry::init_tracing;
+
+struct Options {
+    dry_run: bool,
+    revoke: bool,
+    user_id: Option<Uuid>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options { dry_run: false, revoke: false, user_id: None };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => options.dry_run = true,
+            "--revoke" => options.revoke = true,
+            "--user" => {
+                let raw = args.next().ok_or("--user needs a user id")?;
+                options.user_id = Some(Uuid::parse_str(&raw).map_err(|e| format!("invalid user id '{}': {}", raw, e))?);
+            }
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+    Ok(options)
+}
+
+// An award the replay says the user should hold
+#[derive(Clone, Debug)]
+struct Award {
+    achievement_id: i32,
+    session_id: i64,
+    earned_at: String,
+}
+
+// A row already in user_achievements
+#[derive(Clone, Debug)]
+struct ExistingAward {
+    id: i64,
+    achievement_id: i32,
+    session_id: Option<i64>,
+    earned_at: Option<String>,
+    metadata: serde_json::Value,
+}
+
+#[derive(Debug)]
+enum Change {
+    Add(Award),
+    Correct { existing: ExistingAward, award: Award },
+    Revoke { existing: ExistingAward, reason: &'static str },
+}
+
+const DUPLICATE_AWARD: &str = "backfill: duplicate award";
+const NOT_EARNED_ON_REPLAY: &str = "backfill: criteria not met on replay";
+
+// Walk the sessions in order, each seeing the user's history as it stood then, and record the
+// first session at which every achievement's criteria were met. Past the per-session cap an
+// achievement stays unearned and can still come from a later session, as in the live check.
+async fn replay_user(
+    config: &SupabaseConfig,
+    user_id: Uuid,
+    achievements: &[&Achievement],
+    max_awards: usize,
+    minimums: SessionMinimums,
+) -> Result<Vec<Award>, SupabaseError> {
+    let sessions = Query::table("ruck_session")
+        .select("*")
+        .eq("user_id", user_id)
+        .eq("status", "completed")
+        .order("started_at", false)
+        .order("id", false)
+        .admin()
+        .execute_all(config)
+        .await?;
+    let mut replayed: Vec<(i64, HashMap<String, serde_json::Value>)> = Vec::new();
+    for row in sessions {
+        let session: HashMap<String, serde_json::Value> = match serde_json::from_value(row) {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("Skipping unreadable session for user {}: {}", user_id, e);
+                continue;
+            }
+        };
+        if !meets_minimum_session(&session, minimums) {
+            continue;
+        }
+        if let Some(id) = session.get("id").and_then(|v| v.as_i64()) {
+            replayed.push((id, session));
+        }
+    }
+
+    // One load covers every session; history-based criteria cut it off at each session's time
+    let mut stats = UserStats::load_history(config, user_id).await?;
+    let session_ids: Vec<i64> = replayed.iter().map(|(id, _)| *id).collect();
+    stats.load_splits(config, &session_ids).await?;
+
+    let mut earned: HashSet<i32> = HashSet::new();
+    let mut awards = Vec::new();
+    for (session_id, session) in replayed {
+        let mut session_awards = 0;
+        for achievement in achievements {
+            if session_awards >= max_awards {
+                break;
+            }
+            if earned.contains(&achievement.id) {
+                continue;
+            }
+            if check_criteria(&session, achievement, &stats, minimums) {
+                session_awards += 1;
+                earned.insert(achievement.id);
+                awards.push(Award {
+                    achievement_id: achievement.id,
+                    session_id,
+                    earned_at: evaluation_time(&session).to_rfc3339(),
+                });
+            }
+        }
+    }
+    Ok(awards)
+}
+
+fn same_instant(a: &str, b: &str) -> bool {
+    match (parse_timestamp(a), parse_timestamp(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Compare the replay against what is stored, limited to the achievements the replay evaluated
+fn diff_awards(replayed: &[Award], existing: &[ExistingAward], evaluated: &HashSet<i32>) -> Vec<Change> {
+    let mut by_achievement: HashMap<i32, Vec<&ExistingAward>> = HashMap::new();
+    for row in existing.iter().filter(|row| evaluated.contains(&row.achievement_id)) {
+        by_achievement.entry(row.achievement_id).or_default().push(row);
+    }
+
+    let mut changes = Vec::new();
+    for award in replayed {
+        let mut rows = by_achievement.remove(&award.achievement_id).unwrap_or_default();
+        if rows.is_empty() {
+            changes.push(Change::Add(award.clone()));
+            continue;
+        }
+        // Keep the row that already matches, else the first inserted; any others are duplicates
+        let keep = rows
+            .iter()
+            .position(|row| row.session_id == Some(award.session_id))
+            .unwrap_or(0);
+        let kept = rows.remove(keep);
+        let matches = kept.session_id == Some(award.session_id)
+            && kept.earned_at.as_deref().is_some_and(|at| same_instant(at, &award.earned_at));
+        if !matches {
+            changes.push(Change::Correct { existing: kept.clone(), award: award.clone() });
+        }
+        changes.extend(rows.into_iter().map(|row| Change::Revoke { existing: row.clone(), reason: DUPLICATE_AWARD }));
+    }
+    for rows in by_achievement.into_values() {
+        changes.extend(rows.into_iter().map(|row| Change::Revoke { existing: row.clone(), reason: NOT_EARNED_ON_REPLAY }));
+    }
+    changes
+}
+
+async fn load_existing_awards(config: &SupabaseConfig, user_id: Uuid) -> Result<Vec<ExistingAward>, SupabaseError> {
+    let rows = Query::table("user_achievements")
+        .select("id, achievement_id, session_id, earned_at, metadata")
+        .eq("user_id", user_id)
+        .order("id", false)
+        .admin()
+        .execute_all(config)
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            Some(ExistingAward {
+                id: row.get("id")?.as_i64()?,
+                achievement_id: row.get("achievement_id")?.as_i64()? as i32,
+                session_id: row.get("session_id").and_then(|v| v.as_i64()),
+                earned_at: row.get("earned_at").and_then(|v| v.as_str()).map(str::to_string),
+                metadata: row.get("metadata").cloned().filter(|m| m.is_object()).unwrap_or_else(|| json!({})),
+            })
+        })
+        .collect())
+}
+
+async fn apply_change(
+    config: &SupabaseConfig,
+    user_id: Uuid,
+    unit_preference: &str,
+    change: &Change,
+    revoke: bool,
+) -> Result<(), SupabaseError> {
+    match change {
+        Change::Add(award) => {
+            let award_data = json!({
+                "user_id": user_id,
+                "achievement_id": award.achievement_id,
+                "session_id": award.session_id,
+                "earned_at": award.earned_at,
+                "metadata": {
+                    "triggered_by_session": award.session_id,
+                    "unit_preference": unit_preference,
+                    "backfilled": true,
+                }
+            });
+            // A live check may have awarded it since the diff was taken
+            Query::table("user_achievements")
+                .insert_ignoring_duplicates(award_data)
+                .on_conflict("user_id,achievement_id")
+                .admin()
+                .execute(config)
+                .await?;
+        }
+        Change::Correct { existing, award } if revoke => {
+            Query::table("user_achievements")
+                .update(json!({
+                    "session_id": award.session_id,
+                    "earned_at": award.earned_at,
+                    "metadata": corrected_metadata(existing),
+                }))
+                .eq("id", existing.id)
+                .admin()
+                .execute(config)
+                .await?;
+        }
+        Change::Revoke { existing, reason } if revoke => {
+            let mut metadata = existing.metadata.clone();
+            metadata["revocation_reason"] = json!(reason);
+            let revoked = Query::rpc("revoke_user_achievement", json!({ "p_user_achievement_id": existing.id, "p_metadata": metadata }))
+                .admin()
+                .execute(config)
+                .await?;
+            // Already revoked by a live re-evaluation since the diff was taken
+            if revoked.data != json!(true) {
+                warn!("Award {} for user {} was already gone", existing.id, user_id);
+            }
+        }
+        Change::Correct { .. } | Change::Revoke { .. } => {}
+    }
+    Ok(())
+}
+
+// The award's metadata with the values being replaced appended to `backfill_corrections`
+fn corrected_metadata(existing: &ExistingAward) -> serde_json::Value {
+    let mut metadata = existing.metadata.clone();
+    let correction = json!({
+        "previous_session_id": existing.session_id,
+        "previous_earned_at": existing.earned_at,
+        "corrected_at": Utc::now().to_rfc3339(),
+    });
+    match metadata.get_mut("backfill_corrections").and_then(|v| v.as_array_mut()) {
+        Some(corrections) => corrections.push(correction),
+        None => metadata["backfill_corrections"] = json!([correction]),
+    }
+    metadata
+}
+
+fn describe(user_id: Uuid, change: &Change, keys: &HashMap<i32, &str>) -> String {
+    let key = |id: &i32| keys.get(id).copied().unwrap_or("<unknown>");
+    let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+    match change {
+        Change::Add(award) => format!(
+            "+ user={} achievement={} session={} earned_at={}",
+            user_id,
+            key(&award.achievement_id),
+            award.session_id,
+            award.earned_at
+        ),
+        Change::Correct { existing, award } => format!(
+            "~ user={} achievement={} session={}->{} earned_at={}->{}",
+            user_id,
+            key(&award.achievement_id),
+            or_none(existing.session_id.map(|id| id.to_string())),
+            award.session_id,
+            or_none(existing.earned_at.clone()),
+            award.earned_at
+        ),
+        Change::Revoke { existing, reason } => format!(
+            "- user={} achievement={} session={} earned_at={} (row {}, {})",
+            user_id,
+            key(&existing.achievement_id),
+            or_none(existing.session_id.map(|id| id.to_string())),
+            or_none(existing.earned_at.clone()),
+            existing.id,
+            reason
+        ),
+    }
+}
+
+#[derive(Default)]
+struct Totals {
+    users: usize,
+    added: usize,
+    corrected: usize,
+    revoked: usize,
+    failed: usize,
+}
+
+#[actix_web::main]
+async fn main() {
+    init_tracing();
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\nusage: backfill_achievements [--dry-run] [--revoke] [--user <uuid>]", e);
+            std::process::exit(2);
+        }
+    };
+    // Same settings as the API server, so the replay applies the same session minimums
+    let settings = match ServerConfig::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let config = settings.supabase.clone();
+
+    let achievement_rows = match Query::table("achievements").select("*").eq("is_active", true).order("id", false).admin().execute_all(&config).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Could not load achievements: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let (achievements, errors) = parse_achievements(&achievement_rows);
+    for e in &errors {
+        error!("{} (skipped)", e);
+    }
+    let keys: HashMap<i32, &str> = achievements.iter().map(|a| (a.id, a.achievement_key.as_str())).collect();
+
+    let mut user_query = Query::table("user").select("id, prefer_metric").order("id", false).admin();
+    if let Some(user_id) = options.user_id {
+        user_query = user_query.eq("id", user_id);
+    }
+    let users = match user_query.execute_all(&config).await {
+        Ok(users) => users,
+        Err(e) => {
+            error!("Could not load users: {}", e);
+            std::process::exit(1);
+        }
+    };
+    info!(
+        "Backfilling {} users against {} achievements (dry_run={}, revoke={})",
+        users.len(),
+        achievements.len(),
+        options.dry_run,
+        options.revoke
+    );
+
+    let mut totals = Totals::default();
+    for user in &users {
+        let user_id = match user.get("id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok()) {
+            Some(user_id) => user_id,
+            None => continue,
+        };
+        let prefer_metric = user.get("prefer_metric").and_then(|v| v.as_bool()).unwrap_or(true);
+        let unit_preference = if prefer_metric { "metric" } else { "standard" };
+        // Same selection as the live check: universal achievements plus the user's unit system
+        let applicable: Vec<&Achievement> = achievements
+            .iter()
+            .filter(|a| a.unit_preference.as_deref().is_none_or(|unit| unit == unit_preference))
+            .collect();
+        let evaluated: HashSet<i32> = applicable.iter().map(|a| a.id).collect();
+
+        let (replayed, existing) = match (
+            replay_user(&config, user_id, &applicable, settings.max_awards_per_session, settings.session_minimums).await,
+            load_existing_awards(&config, user_id).await,
+        ) {
+            (Ok(replayed), Ok(existing)) => (replayed, existing),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Skipping user {}: {}", user_id, e);
+                totals.failed += 1;
+                continue;
+            }
+        };
+        totals.users += 1;
+
+        for change in diff_awards(&replayed, &existing, &evaluated) {
+            println!("{}", describe(user_id, &change, &keys));
+            match &change {
+                Change::Add(_) => totals.added += 1,
+                Change::Correct { .. } => totals.corrected += 1,
+                Change::Revoke { .. } => totals.revoked += 1,
+            }
+            if options.dry_run {
+                continue;
+            }
+            if let Err(e) = apply_change(&config, user_id, unit_preference, &change, options.revoke).await {
+                error!("Failed to apply change for user {}: {}", user_id, e);
+                totals.failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "{}users={} add={} correct={} revoke={}{} failed={}",
+        if options.dry_run { "[dry run] " } else { "" },
+        totals.users,
+        totals.added,
+        totals.corrected,
+        totals.revoked,
+        if options.revoke || options.dry_run { "" } else { " (corrections and revocations not applied, pass --revoke)" },
+        totals.failed
+    );
+    if totals.failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn award(achievement_id: i32, session_id: i64, earned_at: &str) -> Award {
+        Award { achievement_id, session_id, earned_at: earned_at.to_string() }
+    }
+
+    fn existing(id: i64, achievement_id: i32, session_id: i64, earned_at: &str) -> ExistingAward {
+        ExistingAward {
+            id,
+            achievement_id,
+            session_id: Some(session_id),
+            earned_at: Some(earned_at.to_string()),
+            metadata: json!({ "unit_preference": "metric" }),
+        }
+    }
+
+    fn diff(replayed: &[Award], stored: &[ExistingAward]) -> Vec<Change> {
+        diff_awards(replayed, stored, &HashSet::from([1, 2, 3]))
+    }
+
+    #[test]
+    fn matching_awards_are_left_alone() {
+        // Same instant written with a different offset still matches
+        let changes = diff(
+            &[award(1, 10, "2026-03-02T06:30:00+00:00")],
+            &[existing(100, 1, 10, "2026-03-02T07:30:00+01:00")],
+        );
+        assert!(changes.is_empty(), "{:?}", changes);
+    }
+
+    #[test]
+    fn missing_awards_are_added() {
+        let changes = diff(&[award(1, 10, "2026-03-02T06:30:00Z")], &[]);
+        assert!(matches!(changes.as_slice(), [Change::Add(a)] if a.achievement_id == 1 && a.session_id == 10));
+    }
+
+    #[test]
+    fn awards_on_the_wrong_session_or_time_are_corrected() {
+        let changes = diff(
+            &[award(1, 10, "2026-03-02T06:30:00Z"), award(2, 11, "2026-03-03T06:30:00Z")],
+            &[existing(100, 1, 12, "2026-03-04T06:30:00Z"), existing(101, 2, 11, "2026-03-05T06:30:00Z")],
+        );
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[0], Change::Correct { existing, award } if existing.id == 100 && award.session_id == 10));
+        assert!(matches!(&changes[1], Change::Correct { existing, award } if existing.id == 101 && award.session_id == 11));
+    }
+
+    #[test]
+    fn duplicates_and_unearned_awards_are_revoked() {
+        let changes = diff(
+            &[award(1, 10, "2026-03-02T06:30:00Z")],
+            &[
+                existing(100, 1, 12, "2026-03-04T06:30:00Z"),
+                existing(101, 1, 10, "2026-03-02T06:30:00Z"),
+                existing(102, 2, 11, "2026-03-03T06:30:00Z"),
+            ],
+        );
+        // The row already on the replayed session is kept over the first inserted one
+        assert_eq!(changes.len(), 2, "{:?}", changes);
+        assert!(matches!(&changes[0], Change::Revoke { existing, reason } if existing.id == 100 && *reason == DUPLICATE_AWARD));
+        assert!(matches!(&changes[1], Change::Revoke { existing, reason } if existing.id == 102 && *reason == NOT_EARNED_ON_REPLAY));
+    }
+
+    #[test]
+    fn achievements_outside_the_replay_are_untouched() {
+        let changes = diff(&[], &[existing(100, 9, 10, "2026-03-02T06:30:00Z")]);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn corrections_keep_the_replaced_values() {
+        let mut row = existing(100, 1, 12, "2026-03-04T06:30:00Z");
+        let metadata = corrected_metadata(&row);
+        assert_eq!(metadata["unit_preference"], "metric");
+        assert_eq!(metadata["backfill_corrections"][0]["previous_session_id"], 12);
+        assert_eq!(metadata["backfill_corrections"][0]["previous_earned_at"], "2026-03-04T06:30:00Z");
+
+        row.metadata = metadata;
+        row.session_id = None;
+        let metadata = corrected_metadata(&row);
+        assert_eq!(metadata["backfill_corrections"].as_array().map(Vec::len), Some(2));
+        assert_eq!(metadata["backfill_corrections"][1]["previous_session_id"], serde_json::Value::Null);
+    }
+}
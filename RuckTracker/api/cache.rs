@@ -0,0 +1,526 @@
+use async_trait::async_trait;
+use tracing::{debug, error, info, warn};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{CacheBackendKind, ServerConfig};
+use crate::metrics::observe_cache_lookup;
+
+// Cache operations with the semantics of redis_cache_service.py: failures are logged and
+// reported as a miss / false / None rather than surfaced to handlers
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Option<serde_json::Value>;
+    async fn set(&self, key: &str, value: &serde_json::Value, ttl_seconds: u64) -> bool;
+    async fn delete(&self, key: &str) -> bool;
+    // Glob pattern as understood by Redis, e.g. "achievements:all:*"; returns keys deleted
+    async fn delete_pattern(&self, pattern: &str) -> u64;
+    // Adds `amount` (creating the key at 0) and refreshes its TTL; None on error
+    async fn increment(&self, key: &str, amount: i64, ttl_seconds: u64) -> Option<i64>;
+    async fn exists(&self, key: &str) -> bool;
+    // Whether the backend answers at all; always true in memory
+    async fn ping(&self) -> bool;
+    async fn stats(&self) -> CacheStats;
+}
+
+pub type Cache = Arc<dyn CacheBackend>;
+
+// Snapshot for /admin/cache. Hits and misses count `get` calls since this process started;
+// memory is an estimate of the values held in memory, or Redis's used_memory.
+#[derive(Serialize, Clone, Debug)]
+pub struct CacheStats {
+    pub backend: &'static str,
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_ratio: Option<f64>,
+    pub memory_bytes: Option<u64>,
+}
+
+#[derive(Default)]
+struct HitCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HitCounter {
+    fn record(&self, value: &Option<serde_json::Value>) {
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self, backend: &'static str, entries: u64, memory_bytes: Option<u64>) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        CacheStats {
+            backend,
+            entries,
+            hits,
+            misses,
+            hit_ratio: (lookups > 0).then(|| (hits as f64 / lookups as f64 * 1000.0).round() / 1000.0),
+            memory_bytes,
+        }
+    }
+}
+
+// Cache get (deduplicated)
+pub async fn cache_get(cache: &Cache, key: &str) -> Option<serde_json::Value> {
+    let value = cache.get(key).await;
+    observe_cache_lookup(key, value.is_some());
+    value
+}
+
+// Cache set (deduplicated)
+pub async fn cache_set(cache: &Cache, key: &str, value: &serde_json::Value, ttl_seconds: u64) -> bool {
+    cache.set(key, value, ttl_seconds).await
+}
+
+// Redis-style glob: `*` matches any run, `?` matches one character
+fn glob_match(pattern: &str, key: &str) -> bool {
+    let (p, k): (Vec<char>, Vec<char>) = (pattern.chars().collect(), key.chars().collect());
+    let (mut pi, mut ki) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ki < k.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == k[ki]) {
+            pi += 1;
+            ki += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ki));
+            pi += 1;
+        } else if let Some((star_pi, star_ki)) = star {
+            pi = star_pi + 1;
+            ki = star_ki + 1;
+            star = Some((star_pi, star_ki + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+struct MemoryEntry {
+    value: serde_json::Value,
+    expires_at: Instant,
+}
+
+// Single-process cache with TTL eviction and a hard cap on entries
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, MemoryEntry>>,
+    max_entries: usize,
+    counter: HitCounter,
+}
+
+impl MemoryCache {
+    pub fn new(max_entries: usize) -> MemoryCache {
+        MemoryCache {
+            entries: Mutex::new(HashMap::new()),
+            max_entries: max_entries.max(1),
+            counter: HitCounter::default(),
+        }
+    }
+
+    // Drop everything past its TTL; returns how many entries were evicted
+    pub fn purge_expired(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        before - entries.len()
+    }
+
+    fn live_value(entries: &mut HashMap<String, MemoryEntry>, key: &str) -> Option<serde_json::Value> {
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    // Make room for one more key: first expired entries, then whichever expires soonest
+    fn make_room(&self, entries: &mut HashMap<String, MemoryEntry>) {
+        if entries.len() < self.max_entries {
+            return;
+        }
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        while entries.len() >= self.max_entries {
+            let soonest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            match soonest {
+                Some(key) => {
+                    debug!("Memory cache full, evicting '{}'", key);
+                    entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get(&self, key: &str) -> Option<serde_json::Value> {
+        let mut entries = self.entries.lock().unwrap();
+        let value = MemoryCache::live_value(&mut entries, key);
+        self.counter.record(&value);
+        value
+    }
+
+    async fn set(&self, key: &str, value: &serde_json::Value, ttl_seconds: u64) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(key) {
+            self.make_room(&mut entries);
+        }
+        entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value: value.clone(),
+                expires_at: Instant::now() + Duration::from_secs(ttl_seconds),
+            },
+        );
+        true
+    }
+
+    async fn delete(&self, key: &str) -> bool {
+        self.entries.lock().unwrap().remove(key).is_some()
+    }
+
+    async fn delete_pattern(&self, pattern: &str) -> u64 {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, _| !glob_match(pattern, key));
+        let deleted = (before - entries.len()) as u64;
+        if deleted > 0 {
+            info!("Deleted {} keys matching pattern '{}'", deleted, pattern);
+        }
+        deleted
+    }
+
+    async fn increment(&self, key: &str, amount: i64, ttl_seconds: u64) -> Option<i64> {
+        let mut entries = self.entries.lock().unwrap();
+        let current = match MemoryCache::live_value(&mut entries, key) {
+            Some(value) => match value.as_i64() {
+                Some(n) => n,
+                None => {
+                    error!("Error incrementing cache key '{}': value is not an integer", key);
+                    return None;
+                }
+            },
+            None => {
+                self.make_room(&mut entries);
+                0
+            }
+        };
+        let next = current + amount;
+        entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value: serde_json::Value::from(next),
+                expires_at: Instant::now() + Duration::from_secs(ttl_seconds),
+            },
+        );
+        Some(next)
+    }
+
+    async fn exists(&self, key: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        MemoryCache::live_value(&mut entries, key).is_some()
+    }
+
+    async fn ping(&self) -> bool {
+        true
+    }
+
+    async fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let (count, bytes) = entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at > now)
+            .fold((0, 0), |(count, bytes), (key, entry)| (count + 1, bytes + key.len() + entry.value.to_string().len()));
+        self.counter.stats("memory", count, Some(bytes as u64))
+    }
+}
+
+// Redis-backed cache storing values exactly as redis_cache_service.py does (JSON for objects and
+// arrays, plain strings otherwise) so the Python and Rust services can share keys
+pub struct RedisCache {
+    connection: ConnectionManager,
+    counter: HitCounter,
+}
+
+impl RedisCache {
+    pub async fn connect(redis_url: &str) -> redis::RedisResult<RedisCache> {
+        // Heroku Redis uses rediss:// with a self-signed certificate; Python skips verification too
+        let url = if redis_url.starts_with("rediss://") && !redis_url.contains('#') {
+            format!("{}#insecure", redis_url)
+        } else {
+            redis_url.to_string()
+        };
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        info!("Redis cache initialized with URL: {}...", &redis_url[..redis_url.len().min(20)]);
+        Ok(RedisCache { connection, counter: HitCounter::default() })
+    }
+
+    fn serialize(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    async fn get(&self, key: &str) -> Option<serde_json::Value> {
+        let mut conn = self.connection.clone();
+        let value = match conn.get::<_, Option<String>>(key).await {
+            Ok(Some(raw)) => Some(serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw))),
+            Ok(None) => None,
+            Err(e) => {
+                error!("Error getting cache key '{}': {}", key, e);
+                None
+            }
+        };
+        self.counter.record(&value);
+        value
+    }
+
+    async fn set(&self, key: &str, value: &serde_json::Value, ttl_seconds: u64) -> bool {
+        let mut conn = self.connection.clone();
+        match conn.set_ex::<_, _, ()>(key, RedisCache::serialize(value), ttl_seconds).await {
+            Ok(()) => {
+                debug!("Set cache key '{}' with expiration {}s", key, ttl_seconds);
+                true
+            }
+            Err(e) => {
+                error!("Error setting cache key '{}': {}", key, e);
+                false
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str) -> bool {
+        let mut conn = self.connection.clone();
+        match conn.del::<_, u64>(key).await {
+            Ok(deleted) => deleted > 0,
+            Err(e) => {
+                error!("Error deleting cache key '{}': {}", key, e);
+                false
+            }
+        }
+    }
+
+    async fn delete_pattern(&self, pattern: &str) -> u64 {
+        let mut conn = self.connection.clone();
+        // SCAN rather than KEYS so a large keyspace doesn't block Redis
+        let keys: Vec<String> = match conn.scan_match::<_, String>(pattern).await {
+            Ok(mut iter) => {
+                let mut keys = Vec::new();
+                while let Some(key) = iter.next_item().await {
+                    keys.push(key);
+                }
+                keys
+            }
+            Err(e) => {
+                error!("Error deleting cache pattern '{}': {}", pattern, e);
+                return 0;
+            }
+        };
+        if keys.is_empty() {
+            return 0;
+        }
+        match conn.del::<_, u64>(&keys).await {
+            Ok(deleted) => {
+                info!("Deleted {} keys matching pattern '{}'", deleted, pattern);
+                deleted
+            }
+            Err(e) => {
+                error!("Error deleting cache pattern '{}': {}", pattern, e);
+                0
+            }
+        }
+    }
+
+    // INCR and EXPIRE in one transaction, as the Python pipeline does
+    async fn increment(&self, key: &str, amount: i64, ttl_seconds: u64) -> Option<i64> {
+        let mut conn = self.connection.clone();
+        let result: redis::RedisResult<(i64,)> = redis::pipe()
+            .atomic()
+            .incr(key, amount)
+            .expire(key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await;
+        match result {
+            Ok((value,)) => Some(value),
+            Err(e) => {
+                error!("Error incrementing cache key '{}': {}", key, e);
+                None
+            }
+        }
+    }
+
+    async fn exists(&self, key: &str) -> bool {
+        let mut conn = self.connection.clone();
+        match conn.exists::<_, bool>(key).await {
+            Ok(exists) => exists,
+            Err(e) => {
+                error!("Error checking cache key existence '{}': {}", key, e);
+                false
+            }
+        }
+    }
+
+    async fn ping(&self) -> bool {
+        let mut conn = self.connection.clone();
+        let pong: redis::RedisResult<String> = redis::cmd("PING").query_async(&mut conn).await;
+        match pong {
+            Ok(_) => true,
+            Err(e) => {
+                error!("Redis ping failed: {}", e);
+                false
+            }
+        }
+    }
+
+    // Entries is the whole Redis database, which the Python service shares
+    async fn stats(&self) -> CacheStats {
+        let mut conn = self.connection.clone();
+        let entries: redis::RedisResult<u64> = redis::cmd("DBSIZE").query_async(&mut conn).await;
+        let info: redis::RedisResult<String> = redis::cmd("INFO").arg("memory").query_async(&mut conn).await;
+        for e in [entries.as_ref().err(), info.as_ref().err()].into_iter().flatten() {
+            error!("Error reading Redis stats: {}", e);
+        }
+        let used_memory = info.ok().and_then(|info| {
+            info.lines().find_map(|line| line.strip_prefix("used_memory:")?.trim().parse().ok())
+        });
+        self.counter.stats("redis", entries.unwrap_or(0), used_memory)
+    }
+}
+
+// How often the in-memory cache sweeps entries nobody has read since they expired
+const MEMORY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+fn memory_cache(max_entries: usize) -> Cache {
+    let cache = Arc::new(MemoryCache::new(max_entries));
+    let weak = Arc::downgrade(&cache);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(MEMORY_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            match weak.upgrade() {
+                Some(cache) => {
+                    let evicted = cache.purge_expired();
+                    if evicted > 0 {
+                        debug!("Memory cache evicted {} expired entries", evicted);
+                    }
+                }
+                None => break,
+            }
+        }
+    });
+    cache
+}
+
+// Redis when configured (CACHE_BACKEND auto with a REDIS_URL, or redis); otherwise the in-memory
+// cache. Only `auto` falls back when Redis is unreachable; an explicit `redis` is an error.
+pub async fn cache_from_config(config: &ServerConfig) -> Result<Cache, redis::RedisError> {
+    let redis_url = match (config.cache_backend, config.redis_url.as_deref()) {
+        (CacheBackendKind::Memory, _) => {
+            info!("CACHE_BACKEND=memory; using in-memory cache");
+            return Ok(memory_cache(config.memory_cache_max_entries));
+        }
+        (_, Some(url)) => url,
+        (_, None) => {
+            warn!("REDIS_URL/REDIS_TLS_URL not set; using in-memory cache");
+            return Ok(memory_cache(config.memory_cache_max_entries));
+        }
+    };
+    match RedisCache::connect(redis_url).await {
+        Ok(cache) => Ok(Arc::new(cache)),
+        // Only `auto` may fall back; an explicit redis backend that can't connect stops startup
+        Err(e) if config.cache_backend == CacheBackendKind::Auto => {
+            error!("Failed to initialize Redis cache, using in-memory cache: {}", e);
+            Ok(memory_cache(config.memory_cache_max_entries))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn glob_matches_like_redis() {
+        assert!(glob_match("achievements:all:*", "achievements:all:metric"));
+        assert!(glob_match("achievements:all:*", "achievements:all:"));
+        assert!(glob_match("user:?:stats", "user:7:stats"));
+        assert!(glob_match("*:progress:*", "achievements:progress:abc"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("user:?:stats", "user:42:stats"));
+        assert!(!glob_match("achievements:all:*", "achievements:recent"));
+        assert!(!glob_match("exact", "exact:more"));
+    }
+
+    #[actix_web::test]
+    async fn expired_entries_are_misses() {
+        let cache = MemoryCache::new(10);
+        cache.set("gone", &json!(1), 0).await;
+        cache.set("kept", &json!(2), 60).await;
+        assert_eq!(cache.get("gone").await, None);
+        assert!(!cache.exists("gone").await);
+        assert_eq!(cache.get("kept").await, Some(json!(2)));
+        cache.set("gone", &json!(1), 0).await;
+        assert_eq!(cache.purge_expired(), 1);
+    }
+
+    #[actix_web::test]
+    async fn full_cache_evicts_expired_then_soonest_to_expire() {
+        let cache = MemoryCache::new(2);
+        cache.set("short", &json!(1), 10).await;
+        cache.set("long", &json!(2), 100).await;
+        cache.set("new", &json!(3), 50).await;
+        assert!(!cache.exists("short").await);
+        assert!(cache.exists("long").await && cache.exists("new").await);
+
+        let cache = MemoryCache::new(2);
+        cache.set("expired", &json!(1), 0).await;
+        cache.set("short", &json!(2), 10).await;
+        cache.set("new", &json!(3), 50).await;
+        assert!(cache.exists("short").await && cache.exists("new").await);
+        // Overwriting an existing key never evicts
+        cache.set("short", &json!(4), 10).await;
+        assert_eq!(cache.get("new").await, Some(json!(3)));
+    }
+
+    #[actix_web::test]
+    async fn increment_delete_and_exists() {
+        let cache = MemoryCache::new(10);
+        assert_eq!(cache.increment("count", 1, 60).await, Some(1));
+        assert_eq!(cache.increment("count", 5, 60).await, Some(6));
+        cache.set("text", &json!("abc"), 60).await;
+        assert_eq!(cache.increment("text", 1, 60).await, None);
+        assert!(cache.exists("count").await);
+        assert!(cache.delete("count").await);
+        assert!(!cache.delete("count").await);
+        assert!(!cache.exists("count").await);
+        cache.set("a:1", &json!(1), 60).await;
+        cache.set("a:2", &json!(2), 60).await;
+        assert_eq!(cache.delete_pattern("a:*").await, 2);
+    }
+}
@@ -0,0 +1,113 @@
+// Checks ruck_session.power_points against the formula behind the database's generated column
+// (fix_powerpoints_hike_calculation.sql) and reports drift. This stands in for a recompute tool:
+// the column is GENERATED ALWAYS, so Postgres computes it on every write and rejects any UPDATE
+// that sets it, and there is nothing a recompute could write. Drift means the column definition
+// no longer matches power_points.rs, and the fix is a migration on one side or a code change on
+// the other. "?" marks a session with no stored value, "~" one whose stored value disagrees
+// with the formula. Exits 1 when any drift is found, so it can run as a scheduled check.
+//
+//   check_power_points                  check every completed session
+//   check_power_points --user <uuid>    limit to one user
+use tracing::error;
+use std::collections::HashMap;
+use std::env;
+use uuid::Uuid;
+
+use ruck_api::config::ServerConfig;
+use ruck_api::evaluation::session_f64;
+use ruck_api::power_points::{computed_power_points, POWER_POINTS_INPUTS};
+use ruck_api::supabase::Query;
+use ruck_api::telemetry::init_tracing;
+
+// Stored values are NUMERIC; anything closer than this is the same number
+const MISMATCH_EPSILON: f64 = 0.01;
+
+const USAGE: &str = "usage: check_power_points [--user <uuid>]
+
+Reports completed sessions whose stored power_points disagrees with the formula in power_points.rs.
+Nothing is written: ruck_session.power_points is GENERATED ALWAYS, so Postgres recomputes it on
+every write and rejects updates to it. Fix drift with a migration to the column or a change to the
+formula. Exits 1 when any session is missing a value (?) or mismatched (~).";
+
+struct Options {
+    user_id: Option<Uuid>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options { user_id: None };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--user" => {
+                let raw = args.next().ok_or("--user needs a user id")?;
+                options.user_id = Some(Uuid::parse_str(&raw).map_err(|e| format!("invalid user id '{}': {}", raw, e))?);
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+    Ok(options)
+}
+
+#[actix_web::main]
+async fn main() {
+    init_tracing();
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let config = match ServerConfig::load() {
+        Ok(settings) => settings.supabase,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut query = Query::table("ruck_session").select(POWER_POINTS_INPUTS).eq("status", "completed").order("id", false).admin();
+    if let Some(user_id) = options.user_id {
+        query = query.eq("user_id", user_id);
+    }
+    let rows = match query.execute_all(&config).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Could not load sessions: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let (mut missing, mut mismatched) = (0, 0);
+    for row in &rows {
+        let session: HashMap<String, serde_json::Value> = match serde_json::from_value(row.clone()) {
+            Ok(session) => session,
+            Err(_) => continue,
+        };
+        let id = match session.get("id").and_then(|v| v.as_i64()) {
+            Some(id) => id,
+            None => continue,
+        };
+        let computed = computed_power_points(&session);
+        match session_f64(&session, "power_points") {
+            None => {
+                println!("? session {}: no stored value, formula {:.2}", id, computed);
+                missing += 1;
+            }
+            Some(stored) if (stored - computed).abs() > MISMATCH_EPSILON => {
+                println!("~ session {}: stored {:.2}, formula {:.2}", id, stored, computed);
+                mismatched += 1;
+            }
+            Some(_) => {}
+        }
+    }
+
+    println!("sessions={} missing={} mismatched={}", rows.len(), missing, mismatched);
+    if missing + mismatched > 0 {
+        std::process::exit(1);
+    }
+}
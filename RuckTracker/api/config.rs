@@ -0,0 +1,204 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::evaluation::{SessionMinimums, MIN_SESSION_DISTANCE_KM, MIN_SESSION_DURATION_S};
+use crate::supabase::SupabaseConfig;
+
+// Which cache `cache_from_config` builds; `auto` uses Redis when a URL is configured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheBackendKind {
+    Auto,
+    Memory,
+    Redis,
+}
+
+impl FromStr for CacheBackendKind {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<CacheBackendKind, String> {
+        match raw.to_ascii_lowercase().as_str() {
+            "auto" => Ok(CacheBackendKind::Auto),
+            "memory" => Ok(CacheBackendKind::Memory),
+            "redis" => Ok(CacheBackendKind::Redis),
+            _ => Err("expected auto, memory or redis".to_string()),
+        }
+    }
+}
+
+// Everything the API server reads at startup. Each setting comes from its environment variable,
+// else from the TOML file named by CONFIG_FILE (keys are the variable names in lowercase), else
+// its default.
+#[derive(Clone)]
+pub struct ServerConfig {
+    pub supabase: SupabaseConfig,
+    pub jwt_secret: Option<String>,
+    pub admin_users: String,
+    pub host: String,
+    pub port: u16,
+    // None lets actix start one worker per core
+    pub workers: Option<usize>,
+    pub shutdown_timeout_s: u64,
+    pub cache_backend: CacheBackendKind,
+    pub redis_url: Option<String>,
+    pub memory_cache_max_entries: usize,
+    pub achievements_cache_ttl_s: u64,
+    pub achievements_empty_cache_ttl_s: u64,
+    pub recent_cache_ttl_s: u64,
+    pub recent_empty_cache_ttl_s: u64,
+    pub max_awards_per_session: usize,
+    pub session_minimums: SessionMinimums,
+    pub firebase_project_id: Option<String>,
+    // Service account key JSON, from FIREBASE_SERVICE_ACCOUNT_JSON or the file at FIREBASE_SERVICE_ACCOUNT_PATH
+    pub firebase_service_account: Option<String>,
+}
+
+// Every missing or invalid setting found while loading, so they can be fixed in one go
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+// Looks settings up in the environment, then the file, collecting problems instead of stopping
+struct Settings {
+    file: toml::Table,
+    errors: Vec<String>,
+}
+
+impl Settings {
+    fn raw(&self, name: &str) -> Option<String> {
+        if let Ok(value) = env::var(name) {
+            return Some(value);
+        }
+        match self.file.get(&name.to_ascii_lowercase())? {
+            toml::Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+
+    fn optional<T: FromStr>(&mut self, name: &str) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let raw = self.raw(name)?;
+        match raw.trim().parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.push(format!("{} = '{}' is invalid: {}", name, raw, e));
+                None
+            }
+        }
+    }
+
+    fn or<T: FromStr>(&mut self, name: &str, default: T) -> T
+    where
+        T::Err: fmt::Display,
+    {
+        self.optional(name).unwrap_or(default)
+    }
+
+    fn required(&mut self, name: &str) -> String {
+        match self.raw(name).filter(|value| !value.trim().is_empty()) {
+            Some(value) => value,
+            None => {
+                self.errors.push(format!("{} must be set", name));
+                String::new()
+            }
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<toml::Table, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("CONFIG_FILE {} could not be read: {}", path, e))?;
+    contents.parse().map_err(|e| format!("CONFIG_FILE {} is not valid TOML: {}", path, e))
+}
+
+impl ServerConfig {
+    pub fn load() -> Result<ServerConfig, ConfigErrors> {
+        let mut errors = Vec::new();
+        let file = match env::var("CONFIG_FILE") {
+            Ok(path) => read_file(&path).unwrap_or_else(|e| {
+                errors.push(e);
+                toml::Table::new()
+            }),
+            Err(_) => toml::Table::new(),
+        };
+        let mut settings = Settings { file, errors };
+
+        let supabase = SupabaseConfig {
+            url: settings.required("SUPABASE_URL"),
+            anon_key: settings.required("SUPABASE_ANON_KEY"),
+            admin_key: settings.required("SUPABASE_ADMIN_KEY"),
+        };
+        let redis_url = settings.raw("REDIS_URL").or_else(|| settings.raw("REDIS_TLS_URL"));
+        let firebase_service_account = match (settings.raw("FIREBASE_SERVICE_ACCOUNT_JSON"), settings.raw("FIREBASE_SERVICE_ACCOUNT_PATH")) {
+            (Some(json), _) => Some(json),
+            (None, Some(path)) => match std::fs::read_to_string(&path) {
+                Ok(json) => Some(json),
+                Err(e) => {
+                    settings.errors.push(format!("FIREBASE_SERVICE_ACCOUNT_PATH {} could not be read: {}", path, e));
+                    None
+                }
+            },
+            (None, None) => None,
+        };
+        let config = ServerConfig {
+            supabase,
+            jwt_secret: settings.raw("SUPABASE_JWT_SECRET"),
+            admin_users: settings.raw("ADMIN_USERS").unwrap_or_default(),
+            // Heroku routes to $PORT on every interface
+            host: settings.raw("HOST").unwrap_or_else(|| "0.0.0.0".to_string()),
+            port: settings.or("PORT", 8080),
+            workers: settings.optional("WEB_CONCURRENCY"),
+            shutdown_timeout_s: settings.or("SHUTDOWN_TIMEOUT_S", 30),
+            cache_backend: settings.or("CACHE_BACKEND", CacheBackendKind::Auto),
+            redis_url,
+            memory_cache_max_entries: settings.or("MEMORY_CACHE_MAX_ENTRIES", 10_000),
+            achievements_cache_ttl_s: settings.or("ACHIEVEMENTS_CACHE_TTL_S", 1800),
+            achievements_empty_cache_ttl_s: settings.or("ACHIEVEMENTS_EMPTY_CACHE_TTL_S", 300),
+            recent_cache_ttl_s: settings.or("RECENT_ACHIEVEMENTS_CACHE_TTL_S", 600),
+            recent_empty_cache_ttl_s: settings.or("RECENT_ACHIEVEMENTS_EMPTY_CACHE_TTL_S", 300),
+            max_awards_per_session: settings.or("MAX_AWARDS_PER_SESSION", 5),
+            session_minimums: SessionMinimums {
+                duration_s: settings.or("MIN_SESSION_DURATION_S", MIN_SESSION_DURATION_S),
+                distance_km: settings.or("MIN_SESSION_DISTANCE_KM", MIN_SESSION_DISTANCE_KM),
+            },
+            firebase_project_id: settings.raw("FIREBASE_PROJECT_ID"),
+            firebase_service_account,
+        };
+
+        let mut errors = settings.errors;
+        if config.workers == Some(0) {
+            errors.push("WEB_CONCURRENCY must be at least 1".to_string());
+        }
+        if config.cache_backend == CacheBackendKind::Redis && config.redis_url.is_none() {
+            errors.push("CACHE_BACKEND=redis needs REDIS_URL or REDIS_TLS_URL".to_string());
+        }
+        if config.memory_cache_max_entries == 0 {
+            errors.push("MEMORY_CACHE_MAX_ENTRIES must be at least 1".to_string());
+        }
+        if config.firebase_project_id.is_some() != config.firebase_service_account.is_some() {
+            errors.push("FIREBASE_PROJECT_ID and a Firebase service account must be set together".to_string());
+        }
+        let SessionMinimums { duration_s, distance_km } = config.session_minimums;
+        let non_negative = |value: f64| value.is_finite() && value >= 0.0;
+        if !non_negative(duration_s) || !non_negative(distance_km) {
+            errors.push("MIN_SESSION_DURATION_S and MIN_SESSION_DISTANCE_KM must be non-negative numbers".to_string());
+        }
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+}
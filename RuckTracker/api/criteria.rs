@@ -0,0 +1,629 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::models::{KM_PER_MILE, M_PER_FT};
+use crate::weather::WeatherBucket;
+
+// Typed achievement criteria, mirroring every `criteria.type` the Python service understands.
+// Rows are stored as JSONB like {"type": "session_weight", "target": 20.41}. Keys a variant does
+// not define are rejected, so a misspelled optional field can't fall back to its default.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Criteria {
+    // Braced rather than a unit variant so deny_unknown_fields applies to it too
+    FirstRuck {},
+    SingleSessionDistance {
+        target: f64, // km, regardless of unit_preference
+    },
+    SessionDuration {
+        target: f64, // seconds
+    },
+    SessionWeight {
+        target: f64, // kg, regardless of unit_preference
+    },
+    PowerPoints {
+        target: f64,
+    },
+    ElevationGain {
+        target: f64, // m for metric/universal, ft for standard
+    },
+    PaceFasterThan {
+        target: f64, // s/km for metric/universal, s/mile for standard
+        // Session distance band the pace must be set over; either bound may be omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_distance_km: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_distance_km: Option<f64>,
+        // Fraction the band is widened by on each side, e.g. 0.05 accepts 47.5-52.5 km for 50 km
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tolerance: Option<f64>,
+    },
+    PaceSlowerThan {
+        target: f64, // s/km for metric/universal, s/mile for standard
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_distance_km: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_distance_km: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tolerance: Option<f64>,
+    },
+    CumulativeDistance {
+        target: f64, // km, regardless of unit_preference
+    },
+    TimeOfDay {
+        #[serde(default = "default_time_of_day_target")]
+        target: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        before_hour: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after_hour: Option<u32>,
+        // [start, end): from start up to but not including end, wrapping past midnight when start > end
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        between_hours: Option<[u32; 2]>,
+    },
+    DailyStreak {
+        #[serde(default = "default_daily_streak_target")]
+        target: u32,
+        // Missed days (weeks / weekends for the other streaks) bridged without breaking the streak
+        #[serde(default, skip_serializing_if = "is_zero")]
+        grace_days: u32,
+    },
+    WeeklyStreak {
+        #[serde(default = "default_weekly_streak_target")]
+        target: u32,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        grace_days: u32,
+    },
+    WeekendStreak {
+        #[serde(default = "default_weekend_streak_target")]
+        target: u32,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        grace_days: u32,
+    },
+    SessionsInWindow {
+        #[serde(default = "default_sessions_in_window_target")]
+        target: u32,
+        #[serde(default = "default_window_days")]
+        window_days: u32,
+        #[serde(default = "default_min_duration_s")]
+        min_duration_s: f64,
+        #[serde(default = "default_min_distance_km")]
+        min_distance_km: f64,
+    },
+    MonthlyConsistency {
+        #[serde(default = "default_monthly_consistency_target")]
+        target: u32, // consecutive months
+        #[serde(default = "default_min_rucks")]
+        min_rucks: u32, // per month
+    },
+    NegativeSplit {
+        #[serde(default = "default_min_splits")]
+        min_splits: u32,
+        // Fraction by which the second-half pace must beat the first, e.g. 0.02 for 2% faster
+        #[serde(default)]
+        margin: f64,
+    },
+    PaceConsistency {
+        #[serde(default = "default_pace_consistency_target")]
+        target: f64, // max coefficient of variation
+    },
+    PhotoUploads {
+        #[serde(default = "default_photo_uploads_target")]
+        target: u32,
+    },
+    WeatherVariety {
+        #[serde(default = "default_weather_variety_target")]
+        target: u32, // distinct weather buckets across the user's sessions
+    },
+    SessionWeather {
+        condition: WeatherBucket,
+    },
+    SessionTemperature {
+        // °C regardless of unit_preference; exactly one bound, e.g. {"below_c": -10}
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        below_c: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        above_c: Option<f64>,
+    },
+    TotalLikesGiven {
+        #[serde(default = "default_likes_given_target")]
+        target: u32,
+    },
+    TotalLikesReceived {
+        #[serde(default = "default_likes_received_target")]
+        target: u32,
+    },
+    MonthlyDistance {
+        #[serde(default = "default_monthly_distance_target")]
+        target: f64, // km for metric/universal, miles for standard
+    },
+    QuarterlyDistance {
+        #[serde(default = "default_quarterly_distance_target")]
+        target: f64, // km for metric/universal, miles for standard
+    },
+    // Composite rules, evaluated against the same session and user_stats as their children
+    AllOf {
+        criteria: Vec<Criteria>,
+    },
+    AnyOf {
+        criteria: Vec<Criteria>,
+    },
+    Not {
+        criterion: Box<Criteria>,
+    },
+    // Every child must be met by the session being checked itself, e.g. 20 km with 20 kg before 6 am
+    WithinSameSession {
+        criteria: Vec<Criteria>,
+    },
+}
+
+// Deepest nesting of composite criteria accepted from the database
+const MAX_COMPOSITE_DEPTH: usize = 8;
+
+// Defaults match the fallbacks in achievements.py `_check_achievement_criteria`
+fn default_time_of_day_target() -> u32 { 1 }
+fn default_daily_streak_target() -> u32 { 7 }
+fn default_weekly_streak_target() -> u32 { 8 }
+fn default_weekend_streak_target() -> u32 { 4 }
+fn default_sessions_in_window_target() -> u32 { 3 }
+fn default_window_days() -> u32 { 7 }
+fn default_min_duration_s() -> f64 { 300.0 }
+fn default_min_distance_km() -> f64 { 0.5 }
+fn default_min_splits() -> u32 { 2 }
+fn default_monthly_consistency_target() -> u32 { 3 }
+fn default_min_rucks() -> u32 { 4 }
+fn default_pace_consistency_target() -> f64 { 0.1 }
+fn default_photo_uploads_target() -> u32 { 10 }
+fn default_weather_variety_target() -> u32 { 3 }
+fn default_likes_given_target() -> u32 { 100 }
+fn default_likes_received_target() -> u32 { 50 }
+fn default_monthly_distance_target() -> f64 { 50.0 }
+fn default_quarterly_distance_target() -> f64 { 200.0 }
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+// A criteria row that could not be turned into a `Criteria`, keyed by achievement_key
+#[derive(Debug, Clone)]
+pub struct CriteriaError {
+    pub achievement_key: String,
+    pub message: String,
+}
+
+impl fmt::Display for CriteriaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid criteria for achievement '{}': {}", self.achievement_key, self.message)
+    }
+}
+
+impl std::error::Error for CriteriaError {}
+
+impl Criteria {
+    // Strict parse: unknown types, missing/mistyped fields and out-of-range values are all errors
+    pub fn from_value(achievement_key: &str, value: &serde_json::Value) -> Result<Criteria, CriteriaError> {
+        let criteria: Criteria = serde_json::from_value(value.clone()).map_err(|e| CriteriaError {
+            achievement_key: achievement_key.to_string(),
+            message: format!("{} (raw: {})", e, value),
+        })?;
+        criteria.validate().map_err(|message| CriteriaError {
+            achievement_key: achievement_key.to_string(),
+            message,
+        })?;
+        Ok(criteria)
+    }
+
+    // Semantic checks serde can't express
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_at(0)
+    }
+
+    fn validate_at(&self, depth: usize) -> Result<(), String> {
+        let non_negative = |name: &str, value: f64| {
+            if value.is_finite() && value >= 0.0 {
+                Ok(())
+            } else {
+                Err(format!("{} must be a non-negative number, got {}", name, value))
+            }
+        };
+        let hour = |name: &str, value: u32| {
+            if value <= 23 {
+                Ok(())
+            } else {
+                Err(format!("{} must be between 0 and 23, got {}", name, value))
+            }
+        };
+
+        match self {
+            Criteria::FirstRuck {} => Ok(()),
+            Criteria::NegativeSplit { min_splits, margin } => {
+                if *min_splits < 2 {
+                    return Err(format!("min_splits must be at least 2, got {}", min_splits));
+                }
+                if !(margin.is_finite() && (0.0..1.0).contains(margin)) {
+                    return Err(format!("margin must be a fraction in [0, 1), got {}", margin));
+                }
+                Ok(())
+            }
+            Criteria::SingleSessionDistance { target }
+            | Criteria::SessionDuration { target }
+            | Criteria::SessionWeight { target }
+            | Criteria::PowerPoints { target }
+            | Criteria::ElevationGain { target }
+            | Criteria::CumulativeDistance { target }
+            | Criteria::PaceConsistency { target }
+            | Criteria::MonthlyDistance { target }
+            | Criteria::QuarterlyDistance { target } => non_negative("target", *target),
+            Criteria::PaceFasterThan { target, min_distance_km, max_distance_km, tolerance }
+            | Criteria::PaceSlowerThan { target, min_distance_km, max_distance_km, tolerance } => {
+                non_negative("target", *target)?;
+                if *target == 0.0 {
+                    return Err("pace target must be greater than zero".to_string());
+                }
+                if let Some(min) = min_distance_km {
+                    non_negative("min_distance_km", *min)?;
+                }
+                if let Some(max) = max_distance_km {
+                    non_negative("max_distance_km", *max)?;
+                }
+                if let (Some(min), Some(max)) = (min_distance_km, max_distance_km) {
+                    if min > max {
+                        return Err(format!("min_distance_km {} is greater than max_distance_km {}", min, max));
+                    }
+                }
+                match tolerance {
+                    Some(t) if !(t.is_finite() && (0.0..1.0).contains(t)) => {
+                        Err(format!("tolerance must be a fraction in [0, 1), got {}", t))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Criteria::TimeOfDay { before_hour, after_hour, between_hours, .. } => match (before_hour, after_hour, between_hours) {
+                (Some(h), None, None) => hour("before_hour", *h),
+                (None, Some(h), None) => hour("after_hour", *h),
+                (None, None, Some([start, end])) => {
+                    hour("between_hours start", *start)?;
+                    hour("between_hours end", *end)?;
+                    if start == end {
+                        return Err(format!("between_hours must span at least an hour, got [{}, {}]", start, end));
+                    }
+                    Ok(())
+                }
+                (None, None, None) => Err("time_of_day requires before_hour, after_hour or between_hours".to_string()),
+                _ => Err("time_of_day takes exactly one of before_hour/after_hour/between_hours".to_string()),
+            },
+            Criteria::SessionTemperature { below_c, above_c } => match (below_c, above_c) {
+                (Some(t), None) | (None, Some(t)) if t.is_finite() => Ok(()),
+                (Some(_), None) | (None, Some(_)) => Err("session_temperature bound must be a finite number".to_string()),
+                _ => Err("session_temperature takes exactly one of below_c/above_c".to_string()),
+            },
+            Criteria::SessionWeather { .. }
+            | Criteria::DailyStreak { .. }
+            | Criteria::WeeklyStreak { .. }
+            | Criteria::WeekendStreak { .. }
+            | Criteria::PhotoUploads { .. }
+            | Criteria::WeatherVariety { .. }
+            | Criteria::TotalLikesGiven { .. }
+            | Criteria::TotalLikesReceived { .. } => Ok(()),
+            Criteria::SessionsInWindow { window_days, min_duration_s, min_distance_km, .. } => {
+                if *window_days == 0 {
+                    return Err("window_days must be at least 1".to_string());
+                }
+                non_negative("min_duration_s", *min_duration_s)?;
+                non_negative("min_distance_km", *min_distance_km)
+            }
+            Criteria::MonthlyConsistency { target, .. } => {
+                if *target == 0 {
+                    return Err("monthly_consistency target must be at least 1 month".to_string());
+                }
+                Ok(())
+            }
+            Criteria::AllOf { criteria } | Criteria::AnyOf { criteria } | Criteria::WithinSameSession { criteria } => {
+                if depth >= MAX_COMPOSITE_DEPTH {
+                    return Err(format!("composite criteria nested deeper than {} levels", MAX_COMPOSITE_DEPTH));
+                }
+                if criteria.is_empty() {
+                    return Err(format!("{} needs at least one criterion", self.type_name()));
+                }
+                for child in criteria {
+                    if matches!(self, Criteria::WithinSameSession { .. }) && !child.is_session_scoped() {
+                        return Err(format!("{} can't be evaluated within a single session", child.type_name()));
+                    }
+                    child.validate_at(depth + 1)?;
+                }
+                Ok(())
+            }
+            Criteria::Not { criterion } => {
+                if depth >= MAX_COMPOSITE_DEPTH {
+                    return Err(format!("composite criteria nested deeper than {} levels", MAX_COMPOSITE_DEPTH));
+                }
+                criterion.validate_at(depth + 1)
+            }
+        }
+    }
+
+    // Decided by the session row (and its splits) alone, without the user's history
+    pub fn is_session_scoped(&self) -> bool {
+        match self {
+            Criteria::SingleSessionDistance { .. }
+            | Criteria::SessionDuration { .. }
+            | Criteria::SessionWeight { .. }
+            | Criteria::ElevationGain { .. }
+            | Criteria::PaceFasterThan { .. }
+            | Criteria::PaceSlowerThan { .. }
+            | Criteria::TimeOfDay { .. }
+            | Criteria::NegativeSplit { .. }
+            | Criteria::SessionWeather { .. }
+            | Criteria::SessionTemperature { .. } => true,
+            Criteria::AllOf { criteria } | Criteria::AnyOf { criteria } | Criteria::WithinSameSession { criteria } => {
+                criteria.iter().all(Criteria::is_session_scoped)
+            }
+            Criteria::Not { criterion } => criterion.is_session_scoped(),
+            _ => false,
+        }
+    }
+
+    // The same rule for the other unit_preference: elevation (m/ft), pace (s/km, s/mile) and
+    // monthly/quarterly distance (km/miles) targets are converted, everything stored in km/kg is not
+    pub fn converted_to(&self, to_standard: bool) -> Criteria {
+        // `metric_per_standard` is how many of the metric value make up one of the standard value
+        let length = |value: f64, metric_per_standard: f64| {
+            let converted = if to_standard { value / metric_per_standard } else { value * metric_per_standard };
+            (converted * 100.0).round() / 100.0
+        };
+        let mut converted = self.clone();
+        match &mut converted {
+            Criteria::ElevationGain { target } => *target = length(*target, M_PER_FT),
+            Criteria::PaceFasterThan { target, .. } | Criteria::PaceSlowerThan { target, .. } => {
+                *target = length(*target, 1.0 / KM_PER_MILE)
+            }
+            Criteria::MonthlyDistance { target } | Criteria::QuarterlyDistance { target } => {
+                *target = length(*target, KM_PER_MILE)
+            }
+            Criteria::AllOf { criteria } | Criteria::AnyOf { criteria } | Criteria::WithinSameSession { criteria } => {
+                for child in criteria.iter_mut() {
+                    *child = child.converted_to(to_standard);
+                }
+            }
+            Criteria::Not { criterion } => **criterion = criterion.converted_to(to_standard),
+            _ => {}
+        }
+        converted
+    }
+
+    // The `type` tag as stored in the database
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Criteria::FirstRuck {} => "first_ruck",
+            Criteria::SingleSessionDistance { .. } => "single_session_distance",
+            Criteria::SessionDuration { .. } => "session_duration",
+            Criteria::SessionWeight { .. } => "session_weight",
+            Criteria::PowerPoints { .. } => "power_points",
+            Criteria::ElevationGain { .. } => "elevation_gain",
+            Criteria::PaceFasterThan { .. } => "pace_faster_than",
+            Criteria::PaceSlowerThan { .. } => "pace_slower_than",
+            Criteria::CumulativeDistance { .. } => "cumulative_distance",
+            Criteria::TimeOfDay { .. } => "time_of_day",
+            Criteria::DailyStreak { .. } => "daily_streak",
+            Criteria::WeeklyStreak { .. } => "weekly_streak",
+            Criteria::WeekendStreak { .. } => "weekend_streak",
+            Criteria::SessionsInWindow { .. } => "sessions_in_window",
+            Criteria::MonthlyConsistency { .. } => "monthly_consistency",
+            Criteria::NegativeSplit { .. } => "negative_split",
+            Criteria::PaceConsistency { .. } => "pace_consistency",
+            Criteria::PhotoUploads { .. } => "photo_uploads",
+            Criteria::WeatherVariety { .. } => "weather_variety",
+            Criteria::SessionWeather { .. } => "session_weather",
+            Criteria::SessionTemperature { .. } => "session_temperature",
+            Criteria::TotalLikesGiven { .. } => "total_likes_given",
+            Criteria::TotalLikesReceived { .. } => "total_likes_received",
+            Criteria::MonthlyDistance { .. } => "monthly_distance",
+            Criteria::QuarterlyDistance { .. } => "quarterly_distance",
+            Criteria::AllOf { .. } => "all_of",
+            Criteria::AnyOf { .. } => "any_of",
+            Criteria::Not { .. } => "not",
+            Criteria::WithinSameSession { .. } => "within_same_session",
+        }
+    }
+}
+
+// Whether a local start hour falls in a time_of_day window. Bounds match the old
+// count_sessions_before_hour / _after_hour RPCs: before is exclusive, after means a later hour.
+pub fn in_time_window(hour: u32, before_hour: Option<u32>, after_hour: Option<u32>, between_hours: Option<[u32; 2]>) -> bool {
+    match (before_hour, after_hour, between_hours) {
+        (Some(h), _, _) => hour < h,
+        (None, Some(h), _) => hour > h,
+        (None, None, Some([start, end])) if start < end => (start..end).contains(&hour),
+        (None, None, Some([start, end])) => hour >= start || hour < end,
+        (None, None, None) => false,
+    }
+}
+
+// Human-readable form of a time_of_day window, e.g. "before 7:00" or "22:00-4:00"
+pub fn describe_time_window(before_hour: Option<u32>, after_hour: Option<u32>, between_hours: Option<[u32; 2]>) -> String {
+    match (before_hour, after_hour, between_hours) {
+        (Some(h), _, _) => format!("before {}:00", h),
+        (None, Some(h), _) => format!("after {}:59", h),
+        (None, None, Some([start, end])) => format!("{}:00-{}:00", start, end),
+        (None, None, None) => "no window".to_string(),
+    }
+}
+
+// Session distances (km) a pace criterion accepts, widened by `tolerance` on each side
+pub fn pace_distance_range(
+    min_distance_km: Option<f64>,
+    max_distance_km: Option<f64>,
+    tolerance: Option<f64>,
+) -> (f64, f64) {
+    let tolerance = tolerance.unwrap_or(0.0);
+    (
+        min_distance_km.map_or(0.0, |min| min * (1.0 - tolerance)),
+        max_distance_km.map_or(f64::INFINITY, |max| max * (1.0 + tolerance)),
+    )
+}
+
+// Tolerance achievements.py applied around a distance parsed from a pace badge's name
+pub const LEGACY_PACE_TOLERANCE: f64 = 0.05;
+
+// Words in a badge name that mark its number as kilometres or miles
+const KM_WORDS: [&str; 4] = ["km", "kms", "kilometer", "kilometers"];
+const MILE_WORDS: [&str; 3] = ["mi", "mile", "miles"];
+
+// Distance implied by a pace badge's name, for rows that predate explicit bands: one of the numbers
+// achievements.py looked for ("80", "50", "42", "20", "12") with a distance unit. Numbers and units
+// only match as whole words, so "50km" and "20 Mile" count but "Summit 20" and "120km" don't.
+pub fn distance_km_from_name(name: &str) -> Option<f64> {
+    let name = name.to_lowercase();
+    // "50km" splits into "50" and "km"
+    let mut words: Vec<&str> = Vec::new();
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        match word.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(at) if at > 0 && word[..at].bytes().all(|b| b.is_ascii_digit()) => {
+                words.extend([&word[..at], &word[at..]]);
+            }
+            _ => words.push(word),
+        }
+    }
+    let km_per_unit = if words.iter().any(|w| KM_WORDS.contains(w)) {
+        1.0
+    } else if words.iter().any(|w| MILE_WORDS.contains(w)) {
+        KM_PER_MILE
+    } else {
+        return None;
+    };
+    let band = ["80", "50", "42", "20", "12"].into_iter().find(|band| words.contains(band))?;
+    let value: f64 = band.parse().ok()?;
+    Some(value * km_per_unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(value: serde_json::Value) -> Result<Criteria, CriteriaError> {
+        Criteria::from_value("test_badge", &value)
+    }
+
+    #[test]
+    fn parses_targets_and_fills_python_defaults() {
+        assert_eq!(parse(json!({ "type": "session_weight", "target": 20.41 })).unwrap(), Criteria::SessionWeight { target: 20.41 });
+        assert_eq!(
+            parse(json!({ "type": "sessions_in_window" })).unwrap(),
+            Criteria::SessionsInWindow { target: 3, window_days: 7, min_duration_s: 300.0, min_distance_km: 0.5 }
+        );
+        assert_eq!(parse(json!({ "type": "first_ruck" })).unwrap(), Criteria::FirstRuck {});
+    }
+
+    #[test]
+    fn rejects_unknown_types_and_missing_or_mistyped_fields() {
+        assert!(parse(json!({ "type": "moon_walk", "target": 1 })).is_err());
+        assert!(parse(json!({ "type": "session_weight" })).is_err());
+        assert!(parse(json!({ "type": "session_weight", "target": "heavy" })).is_err());
+        assert!(parse(json!({ "target": 5 })).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let error = parse(json!({ "type": "negative_split", "min_split": 3 })).unwrap_err();
+        assert!(error.to_string().contains("min_split"), "{}", error);
+        assert!(parse(json!({ "type": "session_weight", "target": 20, "unit": "kg" })).is_err());
+        assert!(parse(json!({ "type": "first_ruck", "target": 1 })).is_err());
+        assert!(parse(json!({ "type": "all_of", "criteria": [{ "type": "session_duration", "target": 60, "targt": 1 }] })).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        assert!(parse(json!({ "type": "single_session_distance", "target": -1.0 })).is_err());
+        assert!(parse(json!({ "type": "pace_faster_than", "target": 0.0 })).is_err());
+        assert!(parse(json!({ "type": "negative_split", "min_splits": 1 })).is_err());
+        assert!(parse(json!({ "type": "negative_split", "margin": 1.0 })).is_err());
+        assert!(parse(json!({ "type": "sessions_in_window", "window_days": 0 })).is_err());
+        assert!(parse(json!({ "type": "monthly_consistency", "target": 0 })).is_err());
+    }
+
+    #[test]
+    fn error_names_the_achievement() {
+        let error = parse(json!({ "type": "session_weight" })).unwrap_err();
+        assert_eq!(error.achievement_key, "test_badge");
+        assert!(error.to_string().contains("test_badge"));
+    }
+
+    #[test]
+    fn type_name_round_trips_through_serde() {
+        let criteria = parse(json!({ "type": "photo_uploads", "target": 3 })).unwrap();
+        assert_eq!(serde_json::to_value(&criteria).unwrap()["type"], criteria.type_name());
+    }
+
+    #[test]
+    fn distance_from_name_converts_miles_to_km() {
+        assert_eq!(distance_km_from_name("50km Speed Demon"), Some(50.0));
+        assert_eq!(distance_km_from_name("Fast 42 KM"), Some(42.0));
+        assert_eq!(distance_km_from_name("20 Mile Pacer"), Some(20.0 * KM_PER_MILE));
+        assert_eq!(distance_km_from_name("12mi Mover"), Some(12.0 * KM_PER_MILE));
+    }
+
+    #[test]
+    fn distance_from_name_needs_whole_words() {
+        assert_eq!(distance_km_from_name("Summit 20"), None);
+        assert_eq!(distance_km_from_name("Minimalist 50"), None);
+        assert_eq!(distance_km_from_name("120km Ultra"), None);
+        assert_eq!(distance_km_from_name("Speed Demon"), None);
+    }
+
+    // `levels` composites wrapped around a single leaf
+    fn nested(levels: usize) -> serde_json::Value {
+        (0..levels).fold(json!({ "type": "session_weight", "target": 10 }), |inner, level| {
+            if level % 2 == 0 {
+                json!({ "type": "all_of", "criteria": [inner] })
+            } else {
+                json!({ "type": "not", "criterion": inner })
+            }
+        })
+    }
+
+    #[test]
+    fn composite_depth_is_limited() {
+        assert!(parse(nested(MAX_COMPOSITE_DEPTH)).is_ok());
+        let error = parse(nested(MAX_COMPOSITE_DEPTH + 1)).unwrap_err();
+        assert!(error.message.contains("nested deeper"));
+    }
+
+    #[test]
+    fn composites_need_children_that_fit_their_scope() {
+        assert!(parse(json!({ "type": "any_of", "criteria": [] })).is_err());
+        assert!(parse(json!({
+            "type": "within_same_session",
+            "criteria": [{ "type": "cumulative_distance", "target": 100 }]
+        }))
+        .is_err());
+        assert!(parse(json!({
+            "type": "within_same_session",
+            "criteria": [{ "type": "single_session_distance", "target": 20 }, { "type": "session_weight", "target": 20 }]
+        }))
+        .is_ok());
+    }
+
+    #[test]
+    fn before_and_after_hours_are_exclusive() {
+        assert!(in_time_window(5, Some(6), None, None));
+        assert!(!in_time_window(6, Some(6), None, None));
+        assert!(in_time_window(21, None, Some(20), None));
+        assert!(!in_time_window(20, None, Some(20), None));
+    }
+
+    #[test]
+    fn between_hours_wraps_midnight() {
+        let window = Some([22, 4]);
+        for hour in [22, 23, 0, 3] {
+            assert!(in_time_window(hour, None, None, window), "{} should be inside 22-4", hour);
+        }
+        for hour in [4, 12, 21] {
+            assert!(!in_time_window(hour, None, None, window), "{} should be outside 22-4", hour);
+        }
+        // Same-day windows include the start hour and exclude the end hour
+        assert!(in_time_window(9, None, None, Some([9, 17])));
+        assert!(!in_time_window(17, None, None, Some([9, 17])));
+        assert!(!in_time_window(3, None, None, Some([9, 17])));
+    }
+}
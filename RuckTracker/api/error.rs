@@ -0,0 +1,103 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use tracing::error;
+use serde_json::json;
+use std::fmt;
+
+use crate::auth::AuthError;
+use crate::supabase::SupabaseError;
+
+// Everything a handler can fail with. Each variant answers with the `{ "error": ... }` body and
+// status code the Python resources use for the same situation.
+#[derive(Debug)]
+pub enum AppError {
+    // A PostgREST call failed; `message` is what the client sees, e.g. "Failed to fetch achievements"
+    Upstream { message: &'static str, source: SupabaseError },
+    // A row or payload didn't have the shape we expected
+    Decode { message: String },
+    NotFound(&'static str),
+    Auth(AuthError),
+    Validation(String),
+    Conflict(String),
+    // Stored data the request depends on can't be used, e.g. an achievement with invalid criteria
+    Unprocessable(String),
+}
+
+impl AppError {
+    // Logs the offending row so bad data can be found without reproducing the request
+    pub fn decode(what: &str, row: &serde_json::Value, e: impl fmt::Display) -> AppError {
+        error!("Could not decode {}: {} (row: {})", what, e, row);
+        AppError::Decode { message: format!("Could not decode {}", what) }
+    }
+
+    // Replace the client-facing message of an upstream failure; other errors keep theirs
+    pub fn upstream_message(self, message: &'static str) -> AppError {
+        match self {
+            AppError::Upstream { source, .. } => AppError::Upstream { message, source },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Upstream { message, source } => write!(f, "{}: {}", message, source),
+            AppError::Decode { message } => write!(f, "{}", message),
+            AppError::NotFound(message) => write!(f, "{}", message),
+            AppError::Auth(e) => write!(f, "{}", e),
+            AppError::Validation(message) | AppError::Conflict(message) | AppError::Unprocessable(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Upstream { .. } | AppError::Decode { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Auth(e) => e.status_code(),
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let message = match self {
+            AppError::Upstream { message, source } => {
+                error!("{}: {}", message, source);
+                message.to_string()
+            }
+            other => other.to_string(),
+        };
+        HttpResponse::build(self.status_code()).json(json!({ "error": message }))
+    }
+}
+
+impl From<SupabaseError> for AppError {
+    fn from(source: SupabaseError) -> AppError {
+        AppError::Upstream { message: "Internal server error", source }
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(e: AuthError) -> AppError {
+        AppError::Auth(e)
+    }
+}
+
+// Attach the client-facing message a failed PostgREST call should surface as
+pub trait UpstreamContext<T> {
+    fn context(self, message: &'static str) -> Result<T, AppError>;
+}
+
+impl<T> UpstreamContext<T> for Result<T, SupabaseError> {
+    fn context(self, message: &'static str) -> Result<T, AppError> {
+        self.map_err(|source| AppError::Upstream { message, source })
+    }
+}
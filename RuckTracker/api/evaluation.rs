@@ -0,0 +1,591 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Timelike, Utc};
+use tracing::{debug, debug_span};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::criteria::{
+    describe_time_window, distance_km_from_name, in_time_window, pace_distance_range, Criteria, LEGACY_PACE_TOLERANCE,
+};
+use crate::models::{Achievement, KM_PER_MILE, M_PER_FT};
+use crate::stats::UserStats;
+use crate::weather::{classify, parse_weather};
+
+// Global minimum a session must meet before any achievement is considered, unless configured
+pub const MIN_SESSION_DURATION_S: f64 = 300.0;
+pub const MIN_SESSION_DISTANCE_KM: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SessionMinimums {
+    pub duration_s: f64,
+    pub distance_km: f64,
+}
+
+// PostgREST numeric/text columns and NUMERIC RPC results may arrive as numbers or strings
+pub fn value_f64(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn session_f64(session: &HashMap<String, serde_json::Value>, key: &str) -> Option<f64> {
+    value_f64(session.get(key)?)
+}
+
+pub fn meets_minimum_session(session: &HashMap<String, serde_json::Value>, minimums: SessionMinimums) -> bool {
+    let duration = session_f64(session, "duration_seconds").unwrap_or(0.0);
+    let distance = session_f64(session, "distance_km").unwrap_or(0.0);
+    duration >= minimums.duration_s && distance >= minimums.distance_km
+}
+
+// Supabase returns timestamptz as RFC 3339; tolerate naive timestamps as UTC
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|dt| dt.and_utc())
+        })
+}
+
+// The moment a session is evaluated at: criteria that look at the user's history only see what
+// existed by then, so replaying old sessions gives the same answer the live check would have
+pub fn evaluation_time(session: &HashMap<String, serde_json::Value>) -> DateTime<Utc> {
+    ["completed_at", "started_at"]
+        .iter()
+        .find_map(|key| session.get(*key).and_then(|v| v.as_str()).and_then(parse_timestamp))
+        .unwrap_or_else(Utc::now)
+}
+
+// Check if user meets criteria for a specific achievement
+pub fn check_criteria(
+    session: &HashMap<String, serde_json::Value>,
+    achievement: &Achievement,
+    stats: &UserStats,
+    minimums: SessionMinimums,
+) -> bool {
+    meets_minimum_session(session, minimums) && explain_criteria(session, achievement, stats, minimums, false).passed
+}
+
+// One evaluated condition: what was observed, the target it was compared with (after unit
+// conversion) and whether it passed. Composite criteria carry their children.
+#[derive(Serialize, Clone, Debug)]
+pub struct ConditionResult {
+    pub condition: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub observed: serde_json::Value,
+    pub target: serde_json::Value,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ConditionResult>,
+}
+
+impl ConditionResult {
+    fn new(
+        condition: &'static str,
+        observed: impl Into<serde_json::Value>,
+        target: impl Into<serde_json::Value>,
+        passed: bool,
+    ) -> ConditionResult {
+        ConditionResult {
+            condition,
+            detail: None,
+            observed: observed.into(),
+            target: target.into(),
+            passed,
+            children: Vec::new(),
+        }
+    }
+
+    fn detail(mut self, detail: impl Into<String>) -> ConditionResult {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+// Evaluate an achievement's criteria and return the full breakdown. With `trace` set, composite
+// rules evaluate every child instead of stopping at the first one that decides the outcome.
+pub fn explain_criteria(
+    session: &HashMap<String, serde_json::Value>,
+    achievement: &Achievement,
+    stats: &UserStats,
+    minimums: SessionMinimums,
+    trace: bool,
+) -> ConditionResult {
+    let input = EvaluationInput {
+        session,
+        achievement,
+        stats,
+        minimums,
+        is_standard: achievement.unit_preference.as_deref() == Some("standard"),
+        as_of: evaluation_time(session),
+        trace,
+    };
+    let _span = debug_span!("criteria", achievement_key = %achievement.achievement_key, as_of = %input.as_of).entered();
+    let result = evaluate(&input, &achievement.criteria, false);
+    debug!(passed = result.passed, condition = result.condition, "criteria evaluated");
+    result
+}
+
+// Everything a criterion is evaluated against
+#[derive(Clone, Copy)]
+struct EvaluationInput<'a> {
+    session: &'a HashMap<String, serde_json::Value>,
+    achievement: &'a Achievement,
+    stats: &'a UserStats,
+    minimums: SessionMinimums,
+    is_standard: bool,
+    as_of: DateTime<Utc>,
+    trace: bool,
+}
+
+// `same_session` is set under within_same_session and makes time_of_day look at this session's
+// local start instead of counting the user's history
+fn evaluate(input: &EvaluationInput, criteria: &Criteria, same_session: bool) -> ConditionResult {
+    let EvaluationInput { session, stats, minimums, is_standard, as_of, .. } = *input;
+    let tz = stats.timezone;
+    let name = criteria.type_name();
+    let at_least = |observed: f64, target: f64| ConditionResult::new(name, observed, target, observed >= target);
+    let count_at_least =
+        |observed: u64, target: u32| ConditionResult::new(name, observed, target, observed >= u64::from(target));
+    let session_start = session.get("started_at").and_then(|v| v.as_str()).and_then(parse_timestamp);
+
+    match criteria {
+        Criteria::FirstRuck {} => {
+            // Qualifying sessions up to this one; the award goes to the user's first completed ruck
+            // that meets the global minimums
+            let qualifying = session_start.map(|start| stats.qualifying_sessions(start, minimums));
+            debug!(?qualifying, ?session_start, "first_ruck qualifying sessions");
+            ConditionResult::new(name, qualifying, 1, qualifying == Some(1))
+                .detail("qualifying completed sessions up to and including this one")
+        }
+        // Distance and weight targets are stored in km/kg regardless of unit_preference
+        Criteria::SingleSessionDistance { target } => {
+            at_least(session_f64(session, "distance_km").unwrap_or(0.0), *target).detail("session distance (km)")
+        }
+        Criteria::SessionDuration { target } => {
+            at_least(session_f64(session, "duration_seconds").unwrap_or(0.0), *target).detail("session duration (s)")
+        }
+        Criteria::SessionWeight { target } => {
+            at_least(session_f64(session, "ruck_weight_kg").unwrap_or(0.0), *target).detail("ruck weight (kg)")
+        }
+        Criteria::ElevationGain { target } => {
+            let target_m = if is_standard { target * M_PER_FT } else { *target };
+            at_least(session_f64(session, "elevation_gain_m").unwrap_or(0.0), target_m).detail("elevation gain (m)")
+        }
+        Criteria::PaceFasterThan { target, min_distance_km, max_distance_km, tolerance } => {
+            let target_s_per_km = if is_standard { target / KM_PER_MILE } else { *target };
+            let (min_distance_km, max_distance_km, tolerance) =
+                legacy_pace_band(input, *min_distance_km, *max_distance_km, *tolerance);
+            let band = pace_distance_condition(input, min_distance_km, max_distance_km, tolerance);
+            let pace = session_f64(session, "average_pace");
+            let pace_check = ConditionResult::new("pace", pace, target_s_per_km, pace.is_some_and(|p| p <= target_s_per_km))
+                .detail("average pace (s/km) at or below target");
+            composite(name, vec![band, pace_check], true)
+        }
+        Criteria::PaceSlowerThan { target, min_distance_km, max_distance_km, tolerance } => {
+            let target_s_per_km = if is_standard { target / KM_PER_MILE } else { *target };
+            let band = pace_distance_condition(input, *min_distance_km, *max_distance_km, *tolerance);
+            let pace = session_f64(session, "average_pace");
+            let pace_check = ConditionResult::new("pace", pace, target_s_per_km, pace.is_some_and(|p| p >= target_s_per_km))
+                .detail("average pace (s/km) at or above target");
+            composite(name, vec![band, pace_check], true)
+        }
+        Criteria::CumulativeDistance { target } => {
+            at_least(stats.total_distance(as_of), *target).detail("lifetime distance (km)")
+        }
+        Criteria::PowerPoints { target } => {
+            at_least(stats.total_power_points(as_of), *target).detail("lifetime power points")
+        }
+        Criteria::TimeOfDay { before_hour, after_hour, between_hours, .. } if same_session => {
+            let hour = session_start.map(|started_at| started_at.with_timezone(&tz).hour());
+            let passed = hour.is_some_and(|hour| in_time_window(hour, *before_hour, *after_hour, *between_hours));
+            ConditionResult::new(name, hour, describe_time_window(*before_hour, *after_hour, *between_hours), passed)
+                .detail(format!("session start hour in {}", tz.name()))
+        }
+        Criteria::TimeOfDay { target, before_hour, after_hour, between_hours } => {
+            let count = stats
+                .history(as_of)
+                .filter(|s| in_time_window(s.started_at.with_timezone(&tz).hour(), *before_hour, *after_hour, *between_hours))
+                .count() as u64;
+            count_at_least(count, *target).detail(format!(
+                "sessions started {} in {}",
+                describe_time_window(*before_hour, *after_hour, *between_hours),
+                tz.name()
+            ))
+        }
+        Criteria::DailyStreak { target, grace_days } => {
+            let streaks = stats.streaks(*grace_days, as_of);
+            count_at_least(u64::from(streaks.daily.current), *target)
+                .detail(format!("consecutive days in {} (grace {})", streaks.timezone, grace_days))
+        }
+        Criteria::WeeklyStreak { target, grace_days } => {
+            let streaks = stats.streaks(*grace_days, as_of);
+            count_at_least(u64::from(streaks.weekly.current), *target)
+                .detail(format!("consecutive ISO weeks in {} (grace {})", streaks.timezone, grace_days))
+        }
+        Criteria::WeekendStreak { target, grace_days } => {
+            let streaks = stats.streaks(*grace_days, as_of);
+            count_at_least(u64::from(streaks.weekend.current), *target)
+                .detail(format!("consecutive weekends in {} (grace {})", streaks.timezone, grace_days))
+        }
+        Criteria::SessionsInWindow { target, window_days, min_duration_s, min_distance_km } => {
+            // None when the session has no start time
+            let count = session_start.map(|end| {
+                let start = end - Duration::days(i64::from((*window_days).max(1)) - 1);
+                stats.sessions_between(start, end, *min_duration_s, *min_distance_km)
+            });
+            debug!(window_days, ?count, "sessions_in_window count");
+            ConditionResult::new(name, count, *target, count.is_some_and(|c| c >= u64::from(*target))).detail(format!(
+                "sessions of {}s+ and {}km+ in the {} days ending with this one",
+                min_duration_s, min_distance_km, window_days
+            ))
+        }
+        Criteria::MonthlyConsistency { target, min_rucks } => {
+            let months = stats.consecutive_active_months(*target, *min_rucks, as_of);
+            count_at_least(u64::from(months), *target).detail(format!("consecutive months with {}+ rucks", min_rucks))
+        }
+        Criteria::NegativeSplit { min_splits, margin } => match split_halves(session, stats, *min_splits) {
+            Some((first_pace, second_pace)) => {
+                let required = first_pace * (1.0 - margin);
+                ConditionResult::new(name, second_pace, required, second_pace < required)
+                    .detail(format!("second-half pace (s/km) vs first half {:.1} less {}%", first_pace, margin * 100.0))
+            }
+            None => ConditionResult::new(name, serde_json::Value::Null, *min_splits, false)
+                .detail("not enough splits recorded for this session"),
+        },
+        Criteria::PaceConsistency { target } => {
+            let cv = stats.pace_variation(as_of);
+            ConditionResult::new(name, cv, *target, cv.is_some_and(|cv| cv <= *target))
+                .detail("coefficient of variation of pace over the last 10 sessions")
+        }
+        Criteria::PhotoUploads { target } => count_at_least(stats.photos_at(as_of), *target),
+        Criteria::WeatherVariety { target } => {
+            let buckets = stats.weather_buckets(as_of);
+            ConditionResult::new(name, json!(buckets), *target, buckets.len() >= *target as usize)
+                .detail("distinct weather conditions rucked in")
+        }
+        Criteria::SessionWeather { condition } => {
+            let buckets = session.get("weather_conditions").and_then(parse_weather).map(|w| classify(&w));
+            let passed = buckets.as_ref().is_some_and(|b| b.contains(condition));
+            ConditionResult::new(name, json!(buckets), json!(condition), passed).detail("this session's weather")
+        }
+        Criteria::SessionTemperature { below_c, above_c } => {
+            let temperature = session.get("weather_conditions").and_then(parse_weather).and_then(|w| w.temperature_c);
+            let (passed, bound) = match (below_c, above_c) {
+                (Some(t), _) => (temperature.is_some_and(|c| c < *t), format!("below {} °C", t)),
+                (None, Some(t)) => (temperature.is_some_and(|c| c > *t), format!("above {} °C", t)),
+                (None, None) => (false, "no bound".to_string()),
+            };
+            ConditionResult::new(name, temperature, bound, passed).detail("temperature during this session (°C)")
+        }
+        Criteria::TotalLikesGiven { target } => count_at_least(stats.likes_given_at(as_of), *target),
+        Criteria::TotalLikesReceived { target } => count_at_least(stats.likes_received_at(as_of), *target),
+        Criteria::MonthlyDistance { target } => {
+            let target_km = if is_standard { target * KM_PER_MILE } else { *target };
+            at_least(stats.monthly_distance(as_of), target_km).detail("distance this month (km)")
+        }
+        Criteria::QuarterlyDistance { target } => {
+            let target_km = if is_standard { target * KM_PER_MILE } else { *target };
+            at_least(stats.quarterly_distance(as_of), target_km).detail("distance this quarter (km)")
+        }
+        Criteria::AllOf { criteria } => composite(name, evaluate_children(input, criteria, same_session, false), true),
+        Criteria::AnyOf { criteria } => composite(name, evaluate_children(input, criteria, same_session, true), false),
+        Criteria::Not { criterion } => {
+            let child = evaluate(input, criterion, same_session);
+            let mut result = ConditionResult::new(name, child.passed, false, !child.passed);
+            result.children.push(child);
+            result
+        }
+        Criteria::WithinSameSession { criteria } => composite(name, evaluate_children(input, criteria, true, false), true),
+    }
+}
+
+// Evaluate children in order, stopping once one comes out as `stop_on` unless tracing
+fn evaluate_children(
+    input: &EvaluationInput,
+    criteria: &[Criteria],
+    same_session: bool,
+    stop_on: bool,
+) -> Vec<ConditionResult> {
+    let mut children = Vec::with_capacity(criteria.len());
+    for child in criteria {
+        let result = evaluate(input, child, same_session);
+        let decided = result.passed == stop_on;
+        children.push(result);
+        if decided && !input.trace {
+            break;
+        }
+    }
+    children
+}
+
+// all_of when `all` is set, any_of otherwise; observed is the number of children that passed
+fn composite(condition: &'static str, children: Vec<ConditionResult>, all: bool) -> ConditionResult {
+    let met = children.iter().filter(|c| c.passed).count();
+    let passed = if all { children.iter().all(|c| c.passed) } else { met > 0 };
+    let mut result = ConditionResult::new(condition, met, if all { children.len() } else { 1 }, passed);
+    result.children = children;
+    result
+}
+
+
+// pace_faster_than rows not yet run through migrate_pace_criteria get the band their name
+// implies, as achievements.py applied it
+fn legacy_pace_band(
+    input: &EvaluationInput,
+    min_distance_km: Option<f64>,
+    max_distance_km: Option<f64>,
+    tolerance: Option<f64>,
+) -> (Option<f64>, Option<f64>, Option<f64>) {
+    if min_distance_km.is_some() || max_distance_km.is_some() {
+        return (min_distance_km, max_distance_km, tolerance);
+    }
+    match distance_km_from_name(&input.achievement.name) {
+        Some(distance_km) => (Some(distance_km), Some(distance_km), Some(LEGACY_PACE_TOLERANCE)),
+        None => (None, None, tolerance),
+    }
+}
+
+// Pace only counts when the session distance falls in the criterion's band, if it has one
+fn pace_distance_condition(
+    input: &EvaluationInput,
+    min_distance_km: Option<f64>,
+    max_distance_km: Option<f64>,
+    tolerance: Option<f64>,
+) -> ConditionResult {
+    let (min_dist, max_dist) = pace_distance_range(min_distance_km, max_distance_km, tolerance);
+    let distance = session_f64(input.session, "distance_km").unwrap_or(0.0);
+    let passed = (min_dist..=max_dist).contains(&distance);
+    if !passed {
+        debug!(distance_km = distance, min_km = min_dist, max_km = max_dist, "pace distance outside band");
+    }
+    let max = if max_dist.is_finite() { json!(max_dist) } else { serde_json::Value::Null };
+    ConditionResult::new("distance_band", distance, json!({ "min_km": min_dist, "max_km": max }), passed)
+        .detail("session distance (km) within the pace badge's band")
+}
+
+// First- and second-half pace (s/km) from the session's splits, or None with fewer than
+// `min_splits` (or splits never loaded into `stats`). With an odd number of splits the middle one
+// goes to the second half, which usually ends in a partial split; each half's pace is its total
+// time over its total distance, and a half with no distance gives None.
+fn split_halves(session: &HashMap<String, serde_json::Value>, stats: &UserStats, min_splits: u32) -> Option<(f64, f64)> {
+    let session_id = session.get("id").and_then(|v| v.as_i64())?;
+    let splits = stats.splits.get(&session_id).map(Vec::as_slice).unwrap_or(&[]);
+    if splits.len() < min_splits.max(2) as usize {
+        debug!(session_id, splits = splits.len(), min_splits, "negative_split: not enough splits");
+        return None;
+    }
+
+    let (first, second) = splits.split_at(splits.len() / 2);
+    let pace = |half: &[(f64, f64)]| {
+        let (distance, duration) = half.iter().fold((0.0, 0.0), |(d, t), (sd, st)| (d + sd, t + st));
+        (distance > 0.0).then(|| duration / distance)
+    };
+    let (first_pace, second_pace) = (pace(first)?, pace(second)?);
+    debug!(session_id, first_half_s_per_km = first_pace, second_half_s_per_km = second_pace, "negative_split halves");
+    Some((first_pace, second_pace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::ActivityCount;
+    use chrono_tz::Tz;
+
+    fn achievement(criteria: serde_json::Value) -> Achievement {
+        Achievement {
+            id: 1,
+            achievement_key: "test_badge".to_string(),
+            name: "Test Badge".to_string(),
+            description: String::new(),
+            category: "test".to_string(),
+            tier: "bronze".to_string(),
+            criteria: Criteria::from_value("test_badge", &criteria).unwrap(),
+            icon_name: None,
+            is_active: true,
+            created_at: None,
+            updated_at: None,
+            unit_preference: None,
+        }
+    }
+
+    fn stats() -> UserStats {
+        UserStats {
+            timezone: Tz::UTC,
+            sessions: Vec::new(),
+            photos: ActivityCount::default(),
+            likes_given: ActivityCount::default(),
+            likes_received: ActivityCount::default(),
+            splits: HashMap::new(),
+        }
+    }
+
+    // 12 km in an hour with 20 kg, started at 05:30 UTC
+    fn session() -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(json!({
+            "id": 7,
+            "started_at": "2026-03-02T05:30:00Z",
+            "completed_at": "2026-03-02T06:30:00Z",
+            "distance_km": 12.0,
+            "duration_seconds": 3600,
+            "ruck_weight_kg": 20.0,
+        }))
+        .unwrap()
+    }
+
+    fn explain_with(criteria: serde_json::Value, stats: &UserStats, trace: bool) -> ConditionResult {
+        let minimums = SessionMinimums { duration_s: MIN_SESSION_DURATION_S, distance_km: MIN_SESSION_DISTANCE_KM };
+        explain_criteria(&session(), &achievement(criteria), stats, minimums, trace)
+    }
+
+    fn explain(criteria: serde_json::Value, trace: bool) -> ConditionResult {
+        explain_with(criteria, &stats(), trace)
+    }
+
+    // Stats with the given (distance_km, duration_seconds) splits loaded for session 7
+    fn with_splits(splits: &[(f64, f64)]) -> UserStats {
+        let mut stats = stats();
+        stats.splits.insert(7, splits.to_vec());
+        stats
+    }
+
+    fn negative_split(splits: &[(f64, f64)], criteria: serde_json::Value) -> ConditionResult {
+        explain_with(criteria, &with_splits(splits), false)
+    }
+
+    #[test]
+    fn all_of_needs_every_child() {
+        let met = json!({ "type": "all_of", "criteria": [
+            { "type": "single_session_distance", "target": 10 },
+            { "type": "session_weight", "target": 20 },
+        ]});
+        assert!(explain(met, false).passed);
+        let missed = json!({ "type": "all_of", "criteria": [
+            { "type": "single_session_distance", "target": 10 },
+            { "type": "session_weight", "target": 25 },
+        ]});
+        let result = explain(missed, false);
+        assert!(!result.passed);
+        assert_eq!(result.observed, json!(1));
+        assert_eq!(result.target, json!(2));
+    }
+
+    #[test]
+    fn any_of_needs_one_child_and_not_inverts() {
+        let any = json!({ "type": "any_of", "criteria": [
+            { "type": "single_session_distance", "target": 50 },
+            { "type": "session_weight", "target": 20 },
+        ]});
+        assert!(explain(any, false).passed);
+        assert!(!explain(json!({ "type": "not", "criterion": { "type": "session_weight", "target": 20 } }), false).passed);
+        assert!(explain(json!({ "type": "not", "criterion": { "type": "session_weight", "target": 30 } }), false).passed);
+    }
+
+    #[test]
+    fn composites_stop_at_the_deciding_child_unless_tracing() {
+        let criteria = json!({ "type": "all_of", "criteria": [
+            { "type": "session_weight", "target": 30 },
+            { "type": "single_session_distance", "target": 10 },
+            { "type": "session_duration", "target": 60 },
+        ]});
+        assert_eq!(explain(criteria.clone(), false).children.len(), 1);
+        let traced = explain(criteria, true);
+        assert!(!traced.passed);
+        assert_eq!(traced.children.len(), 3);
+        assert_eq!(traced.observed, json!(2));
+    }
+
+    #[test]
+    fn within_same_session_uses_this_sessions_start() {
+        // No history at all, so the same rule outside within_same_session counts zero sessions
+        let early = json!({ "type": "time_of_day", "before_hour": 6 });
+        assert!(!explain(early.clone(), false).passed);
+        let combined = json!({ "type": "within_same_session", "criteria": [
+            early,
+            { "type": "session_weight", "target": 20 },
+        ]});
+        assert!(explain(combined, false).passed);
+    }
+
+    #[test]
+    fn nested_composites_evaluate_recursively() {
+        let criteria = json!({ "type": "any_of", "criteria": [
+            { "type": "all_of", "criteria": [
+                { "type": "session_weight", "target": 20 },
+                { "type": "single_session_distance", "target": 40 },
+            ]},
+            { "type": "not", "criterion": { "type": "session_duration", "target": 7200 } },
+        ]});
+        let result = explain(criteria, true);
+        assert!(result.passed);
+        assert!(!result.children[0].passed);
+        assert!(result.children[1].passed);
+    }
+
+    #[test]
+    fn negative_split_compares_halves_of_an_even_split_count() {
+        let splits = [(1.0, 620.0), (1.0, 600.0), (1.0, 590.0), (1.0, 570.0)];
+        let result = negative_split(&splits, json!({ "type": "negative_split" }));
+        assert!(result.passed);
+        assert_eq!(result.observed, json!(580.0));
+        assert_eq!(result.target, json!(610.0));
+        let positive = [(1.0, 570.0), (1.0, 590.0), (1.0, 600.0), (1.0, 620.0)];
+        assert!(!negative_split(&positive, json!({ "type": "negative_split" })).passed);
+    }
+
+    #[test]
+    fn negative_split_puts_the_middle_of_an_odd_count_in_the_second_half() {
+        // Halves are [600] and [660, 500]
+        let result = negative_split(&[(1.0, 600.0), (1.0, 660.0), (1.0, 500.0)], json!({ "type": "negative_split" }));
+        assert!(result.passed);
+        assert_eq!(result.observed, json!(580.0));
+        assert_eq!(result.target, json!(600.0));
+        // A slow middle split counts against the second half rather than being dropped
+        assert!(!negative_split(&[(1.0, 600.0), (1.0, 720.0), (1.0, 500.0)], json!({ "type": "negative_split" })).passed);
+        // A partial last split is weighted by its distance: [660] against 700 s over 1.4 km
+        assert!(negative_split(&[(1.0, 660.0), (1.0, 500.0), (0.4, 200.0)], json!({ "type": "negative_split" })).passed);
+    }
+
+    #[test]
+    fn negative_split_needs_min_splits() {
+        let splits = [(1.0, 620.0), (1.0, 600.0), (1.0, 590.0), (1.0, 570.0)];
+        assert!(negative_split(&splits, json!({ "type": "negative_split", "min_splits": 4 })).passed);
+        let result = negative_split(&splits, json!({ "type": "negative_split", "min_splits": 5 }));
+        assert!(!result.passed);
+        assert_eq!(result.observed, serde_json::Value::Null);
+        assert_eq!(result.target, json!(5));
+        // Two splits are always needed to have two halves
+        assert!(!negative_split(&splits[..1], json!({ "type": "negative_split", "min_splits": 2 })).passed);
+    }
+
+    #[test]
+    fn negative_split_fails_without_split_rows() {
+        // Loaded but empty, never loaded, and splits with no distance
+        assert!(!negative_split(&[], json!({ "type": "negative_split" })).passed);
+        assert!(!explain(json!({ "type": "negative_split" }), false).passed);
+        let no_distance = [(0.0, 600.0), (0.0, 500.0)];
+        let result = negative_split(&no_distance, json!({ "type": "negative_split" }));
+        assert!(!result.passed);
+        assert_eq!(result.observed, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn negative_split_margin_is_strict() {
+        // First half 600 s/km; a 2% margin needs the second half under 588 s/km
+        let criteria = json!({ "type": "negative_split", "margin": 0.02 });
+        let just_under = negative_split(&[(1.0, 600.0), (1.0, 587.9)], criteria.clone());
+        assert!(just_under.passed);
+        assert_eq!(just_under.target, json!(588.0));
+        assert!(!negative_split(&[(1.0, 600.0), (1.0, 588.0)], criteria.clone()).passed);
+        assert!(!negative_split(&[(1.0, 600.0), (1.0, 590.0)], criteria).passed);
+        // With no margin any improvement counts, but an even split does not
+        assert!(negative_split(&[(1.0, 600.0), (1.0, 599.9)], json!({ "type": "negative_split" })).passed);
+        assert!(!negative_split(&[(1.0, 600.0), (1.0, 600.0)], json!({ "type": "negative_split" })).passed);
+    }
+}
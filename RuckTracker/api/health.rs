@@ -0,0 +1,49 @@
+use actix_web::rt::time::timeout;
+use actix_web::{web, HttpResponse};
+use tracing::{instrument, warn};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::cache::Cache;
+use crate::supabase::{Query, SupabaseConfig};
+
+// A dependency slower than this counts as down, so probes never hang
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Liveness: the process is up and serving requests (same body as the Flask app's /health)
+#[instrument(skip_all)]
+pub async fn health_handler() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }))
+}
+
+// Readiness: Supabase REST answers a query made with the admin key, and the cache backend responds.
+// 503 lists which check failed.
+#[instrument(skip_all)]
+pub async fn ready_handler(config: web::Data<SupabaseConfig>, cache: web::Data<Cache>) -> HttpResponse {
+    let supabase = match timeout(READY_CHECK_TIMEOUT, Query::table("achievements").select("id").limit(1).admin().execute(&config)).await {
+        Ok(Ok(_)) => json!({ "ok": true }),
+        Ok(Err(e)) => {
+            warn!("Readiness check: Supabase unavailable: {}", e);
+            json!({ "ok": false, "error": "request failed" })
+        }
+        Err(_) => {
+            warn!("Readiness check: Supabase timed out");
+            json!({ "ok": false, "error": "timed out" })
+        }
+    };
+    let cache_ok = timeout(READY_CHECK_TIMEOUT, cache.ping()).await.unwrap_or(false);
+    if !cache_ok {
+        warn!("Readiness check: cache backend unavailable");
+    }
+
+    let ready = supabase["ok"] == true && cache_ok;
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": { "supabase": supabase, "cache": { "ok": cache_ok } },
+    });
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
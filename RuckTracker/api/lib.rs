@@ -0,0 +1,20 @@
+// Modules shared by the API server and the maintenance binaries
+pub mod admin;
+pub mod auth;
+pub mod cache;
+pub mod config;
+pub mod criteria;
+pub mod error;
+pub mod evaluation;
+pub mod health;
+pub mod metrics;
+pub mod models;
+pub mod notifications;
+pub mod power_points;
+pub mod progress;
+pub mod revocation;
+pub mod stats;
+pub mod streaks;
+pub mod supabase;
+pub mod telemetry;
+pub mod weather;
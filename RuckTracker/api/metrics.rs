@@ -0,0 +1,132 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error as ActixError, HttpResponse};
+use tracing::error;
+use prometheus::{
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry, register_int_counter_with_registry,
+    Encoder, HistogramVec, IntCounter, IntCounterVec, Registry, TextEncoder,
+};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+// Everything exported on /metrics. Labels are route patterns, tables, key prefixes and achievement
+// keys, never ids, so series counts stay bounded.
+struct Metrics {
+    registry: Registry,
+    http_request_duration: HistogramVec,
+    // Calls per table or rpc are the histogram's _count; errors are counted by kind
+    supabase_request_duration: HistogramVec,
+    supabase_errors: IntCounterVec,
+    cache_lookups: IntCounterVec,
+    achievements_awarded: IntCounterVec,
+    sessions_below_minimum: IntCounter,
+}
+
+// PostgREST calls are usually tens of ms; a whole check can take seconds
+const LATENCY_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+impl Metrics {
+    fn new() -> prometheus::Result<Metrics> {
+        let registry = Registry::new_custom(Some("ruck_api".to_string()), None)?;
+        Ok(Metrics {
+            http_request_duration: register_histogram_vec_with_registry!(
+                "http_request_duration_seconds",
+                "HTTP request latency by route pattern",
+                &["method", "route", "status"],
+                LATENCY_BUCKETS.to_vec(),
+                registry
+            )?,
+            supabase_request_duration: register_histogram_vec_with_registry!(
+                "supabase_request_duration_seconds",
+                "PostgREST call latency by table or rpc",
+                &["target", "method"],
+                LATENCY_BUCKETS.to_vec(),
+                registry
+            )?,
+            supabase_errors: register_int_counter_vec_with_registry!(
+                "supabase_errors_total",
+                "Failed PostgREST calls by table or rpc",
+                &["target", "method", "kind"],
+                registry
+            )?,
+            cache_lookups: register_int_counter_vec_with_registry!(
+                "cache_lookups_total",
+                "Cache reads by key prefix and result",
+                &["prefix", "result"],
+                registry
+            )?,
+            achievements_awarded: register_int_counter_vec_with_registry!(
+                "achievements_awarded_total",
+                "Achievements awarded by live checks",
+                &["achievement_key"],
+                registry
+            )?,
+            sessions_below_minimum: register_int_counter_with_registry!(
+                "sessions_below_minimum_total",
+                "Sessions checked but rejected by the minimum duration/distance gate",
+                registry
+            )?,
+            registry,
+        })
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(|| Metrics::new().expect("metric definitions are valid"))
+}
+
+// `target` is the PostgREST path, i.e. the table name or `rpc/<function>`
+pub fn observe_supabase_call(target: &str, method: &str, elapsed: Duration, error_kind: Option<&str>) {
+    let m = metrics();
+    m.supabase_request_duration.with_label_values(&[target, method]).observe(elapsed.as_secs_f64());
+    if let Some(kind) = error_kind {
+        m.supabase_errors.with_label_values(&[target, method, kind]).inc();
+    }
+}
+
+// Keys are `<area>:<kind>:<variant>`; everything before the last segment is the prefix
+pub fn observe_cache_lookup(key: &str, hit: bool) {
+    let prefix = key.rsplit_once(':').map_or(key, |(prefix, _)| prefix);
+    metrics().cache_lookups.with_label_values(&[prefix, if hit { "hit" } else { "miss" }]).inc();
+}
+
+pub fn observe_award(achievement_key: &str) {
+    metrics().achievements_awarded.with_label_values(&[achievement_key]).inc();
+}
+
+pub fn observe_session_below_minimum() {
+    metrics().sessions_below_minimum.inc();
+}
+
+// Time every request, labelled with the matched route pattern rather than the raw path
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixError> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(res) => res.status().as_u16(),
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    };
+    metrics()
+        .http_request_duration
+        .with_label_values(&[method.as_str(), route.as_str(), &status.to_string()])
+        .observe(started.elapsed().as_secs_f64());
+    result
+}
+
+// Prometheus text exposition of everything above
+pub async fn metrics_handler() -> HttpResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&metrics().registry.gather(), &mut buffer) {
+        error!("Failed to encode metrics: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+    HttpResponse::Ok().content_type(encoder.format_type()).body(buffer)
+}
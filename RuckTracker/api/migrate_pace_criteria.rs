@@ -0,0 +1,144 @@
+// One-off migration: pace_faster_than rows whose distance band was inferred from the badge name
+// get explicit min_distance_km / max_distance_km / tolerance in `achievements.criteria`, matching
+// what achievements.py enforced. Rows that already carry a band are left alone.
+//
+// achievements.py divided mile distances by KM_PER_MILE and matched "km"/"mi" anywhere in the
+// name, so some rows' bands change here. Those are listed before anything is written.
+//
+//   migrate_pace_criteria --dry-run    print the rewrites without saving them
+use tracing::error;
+use serde_json::json;
+use std::env;
+
+use ruck_api::config::ServerConfig;
+use ruck_api::criteria::{distance_km_from_name, pace_distance_range, Criteria, LEGACY_PACE_TOLERANCE};
+use ruck_api::models::KM_PER_MILE;
+use ruck_api::supabase::Query;
+use ruck_api::telemetry::init_tracing;
+
+#[actix_web::main]
+async fn main() {
+    init_tracing();
+    let dry_run = env::args().skip(1).any(|arg| arg == "--dry-run");
+    let config = match ServerConfig::load() {
+        Ok(settings) => settings.supabase,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let response = match Query::table("achievements")
+        .select("id, achievement_key, name, criteria")
+        .eq("criteria->>type", "pace_faster_than")
+        .order("id", false)
+        .admin()
+        .execute(&config)
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Could not load pace achievements: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // (id, key, name, old criteria, new criteria) for every row that gets an explicit band
+    let mut rewrites = Vec::new();
+    let mut band_changes = Vec::new();
+    let mut failed = 0;
+    for row in response.rows() {
+        let key = row.get("achievement_key").and_then(|v| v.as_str()).unwrap_or("<missing key>");
+        let name = row.get("name").and_then(|v| v.as_str()).unwrap_or("");
+        let id = match row.get("id").and_then(|v| v.as_i64()) {
+            Some(id) => id,
+            None => continue,
+        };
+        let raw = row.get("criteria").cloned().unwrap_or(serde_json::Value::Null);
+        let target = match Criteria::from_value(key, &raw) {
+            Ok(Criteria::PaceFasterThan { target, min_distance_km: None, max_distance_km: None, .. }) => target,
+            Ok(_) => continue,
+            Err(e) => {
+                error!("{} (skipped)", e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let distance_km = distance_km_from_name(name);
+        let legacy_km = python_distance_km_from_name(name);
+        if distance_km != legacy_km {
+            band_changes.push(format!("{} ({}): {} -> {}", key, name, describe_band(legacy_km), describe_band(distance_km)));
+        }
+        let Some(distance_km) = distance_km else {
+            continue;
+        };
+        let criteria = Criteria::PaceFasterThan {
+            target,
+            min_distance_km: Some(distance_km),
+            max_distance_km: Some(distance_km),
+            tolerance: Some(LEGACY_PACE_TOLERANCE),
+        };
+        let criteria = serde_json::to_value(&criteria).expect("criteria serializes");
+        rewrites.push((id, key, name, raw, criteria));
+    }
+
+    // Whoever was eligible under the Python band but not the new one (or the reverse) is affected
+    println!("Eligibility band changes ({}):", band_changes.len());
+    for change in &band_changes {
+        println!("  {}", change);
+    }
+    for (_, key, name, raw, criteria) in &rewrites {
+        println!("{} ({}): {} -> {}", key, name, raw, criteria);
+    }
+
+    let mut rewritten = 0;
+    for (id, key, _, _, criteria) in rewrites {
+        rewritten += 1;
+        if dry_run {
+            continue;
+        }
+        if let Err(e) = Query::table("achievements")
+            .update(json!({ "criteria": criteria }))
+            .eq("id", id)
+            .admin()
+            .execute(&config)
+            .await
+        {
+            error!("Failed to update achievement {}: {}", key, e);
+            failed += 1;
+        }
+    }
+
+    println!(
+        "{}rewritten={} band_changes={} failed={}",
+        if dry_run { "[dry run] " } else { "" },
+        rewritten,
+        band_changes.len(),
+        failed
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+// The band achievements.py applied: substring matches, with mile values divided by KM_PER_MILE
+fn python_distance_km_from_name(name: &str) -> Option<f64> {
+    let name = name.to_lowercase();
+    if !(name.contains("km") || name.contains("mi")) {
+        return None;
+    }
+    let band = ["80", "50", "42", "20", "12"].iter().find(|band| name.contains(*band))?;
+    let value: f64 = band.parse().ok()?;
+    Some(if name.contains("km") { value } else { value / KM_PER_MILE })
+}
+
+fn describe_band(distance_km: Option<f64>) -> String {
+    match distance_km {
+        Some(distance_km) => {
+            let (min, max) = pace_distance_range(Some(distance_km), Some(distance_km), Some(LEGACY_PACE_TOLERANCE));
+            format!("{:.2}-{:.2} km", min, max)
+        }
+        None => "no band".to_string(),
+    }
+}
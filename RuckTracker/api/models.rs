@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::criteria::{Criteria, CriteriaError};
+
+pub const KM_PER_MILE: f64 = 1.60934;
+pub const M_PER_FT: f64 = 0.3048;
+
+// Row of the `achievements` table, shared by the API server and the maintenance binaries
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Achievement {
+    pub id: i32,
+    pub achievement_key: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub tier: String,
+    pub criteria: Criteria,
+    pub icon_name: Option<String>,
+    pub is_active: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub unit_preference: Option<String>,
+}
+
+// Parse achievement rows, reporting bad criteria by achievement_key instead of dropping them silently
+pub fn parse_achievements(rows: &[serde_json::Value]) -> (Vec<Achievement>, Vec<CriteriaError>) {
+    let mut achievements = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+    for row in rows {
+        let key = row.get("achievement_key").and_then(|v| v.as_str()).unwrap_or("<missing key>");
+        let parsed = Criteria::from_value(key, row.get("criteria").unwrap_or(&serde_json::Value::Null))
+            .and_then(|_| {
+                serde_json::from_value::<Achievement>(row.clone()).map_err(|e| CriteriaError {
+                    achievement_key: key.to_string(),
+                    message: e.to_string(),
+                })
+            });
+        match parsed {
+            Ok(achievement) => achievements.push(achievement),
+            Err(e) => errors.push(e),
+        }
+    }
+    (achievements, errors)
+}
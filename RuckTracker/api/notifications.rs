@@ -0,0 +1,312 @@
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::cache::{cache_get, cache_set, Cache};
+use crate::config::ServerConfig;
+use crate::supabase::{http_client, Query, SupabaseConfig};
+
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+// Access tokens last an hour; refresh a little early like push_notification_service.py
+const ACCESS_TOKEN_LIFETIME: Duration = Duration::from_secs(3300);
+// One push per session and notification type per day, shared with the Python service's key
+const NOTIFICATION_DEDUPE_TTL_S: u64 = 86400;
+
+// The fields of a Firebase service account key we sign token requests with
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    #[serde(default = "default_token_uri")]
+    token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+#[derive(Serialize)]
+struct TokenClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+// Sends through the FCM v1 HTTP API with a service-account OAuth token
+struct FcmSender {
+    project_id: String,
+    client_email: String,
+    token_uri: String,
+    key: EncodingKey,
+    access_token: Mutex<Option<(String, Instant)>>,
+}
+
+// What happened to one device token
+enum Delivery {
+    Sent,
+    // FCM no longer knows the token; it gets deactivated
+    Unregistered,
+    Failed,
+}
+
+impl FcmSender {
+    fn new(project_id: &str, service_account_json: &str) -> Result<FcmSender, String> {
+        let account: ServiceAccount = serde_json::from_str(service_account_json)
+            .map_err(|e| format!("Firebase service account is not valid JSON: {}", e))?;
+        // Heroku config vars can carry the key's newlines double-escaped
+        let private_key = account.private_key.replace("\\n", "\n");
+        let key = EncodingKey::from_rsa_pem(private_key.as_bytes())
+            .map_err(|e| format!("Firebase service account private_key is not a valid RSA key: {}", e))?;
+        Ok(FcmSender {
+            project_id: project_id.to_string(),
+            client_email: account.client_email,
+            token_uri: account.token_uri,
+            key,
+            access_token: Mutex::new(None),
+        })
+    }
+
+    async fn access_token(&self) -> Result<String, String> {
+        if let Some((token, fetched_at)) = self.access_token.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < ACCESS_TOKEN_LIFETIME {
+                return Ok(token.clone());
+            }
+        }
+
+        let now = Utc::now().timestamp();
+        let claims = TokenClaims { iss: &self.client_email, scope: FCM_SCOPE, aud: &self.token_uri, iat: now, exp: now + 3600 };
+        let assertion = encode(&Header::new(Algorithm::RS256), &claims, &self.key).map_err(|e| e.to_string())?;
+        let response: TokenResponse = async {
+            http_client()
+                .post(&self.token_uri)
+                .form(&[("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        }
+        .await
+        .map_err(|e: reqwest::Error| e.to_string())?;
+
+        *self.access_token.lock().unwrap() = Some((response.access_token.clone(), Instant::now()));
+        Ok(response.access_token)
+    }
+
+    // Same message shape as the Firebase Admin SDK call in push_notification_service.py
+    async fn send(&self, access_token: &str, device_token: &str, title: &str, body: &str, data: &serde_json::Value) -> Delivery {
+        let message = json!({
+            "message": {
+                "token": device_token,
+                "notification": { "title": title, "body": body },
+                "data": data,
+                "android": {
+                    "notification": {
+                        "click_action": "FLUTTER_NOTIFICATION_CLICK",
+                        "channel_id": "default",
+                        "notification_priority": "PRIORITY_HIGH",
+                    }
+                },
+                "apns": {
+                    "payload": {
+                        "aps": {
+                            "alert": { "title": title, "body": body },
+                            "category": "FLUTTER_NOTIFICATION_CLICK",
+                            "sound": "default",
+                        }
+                    }
+                },
+            }
+        });
+        let url = format!("https://fcm.googleapis.com/v1/projects/{}/messages:send", self.project_id);
+        let response = match http_client().post(&url).bearer_auth(access_token).json(&message).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                error!("FCM request failed: {}", e);
+                return Delivery::Failed;
+            }
+        };
+        let status = response.status();
+        if status.is_success() {
+            return Delivery::Sent;
+        }
+        let text = response.text().await.unwrap_or_default();
+        if text.contains("UNREGISTERED") {
+            return Delivery::Unregistered;
+        }
+        error!("FCM send failed with {}: {}", status, text);
+        Delivery::Failed
+    }
+}
+
+// Saves notifications to the `notifications` table and pushes them to the recipient's devices,
+// like notification_manager.py. Push is skipped (rows are still saved) without Firebase credentials.
+pub struct Notifier {
+    supabase: SupabaseConfig,
+    cache: Cache,
+    fcm: Option<FcmSender>,
+}
+
+impl Notifier {
+    pub fn new(settings: &ServerConfig, cache: Cache) -> Result<Notifier, String> {
+        let fcm = match (&settings.firebase_project_id, &settings.firebase_service_account) {
+            (Some(project_id), Some(account)) => Some(FcmSender::new(project_id, account)?),
+            _ => {
+                warn!("Firebase credentials not configured; push notifications are disabled");
+                None
+            }
+        };
+        Ok(Notifier { supabase: settings.supabase.clone(), cache, fcm })
+    }
+
+    // Python's send_achievement_notification: one notification for every award a session earned
+    pub async fn send_achievement_notification(&self, recipient_id: Uuid, achievement_names: &[String], session_id: i64) -> bool {
+        let (title, body) = achievement_message(achievement_names);
+        let data = json!({
+            "ruck_id": session_id.to_string(),
+            "session_id": session_id.to_string(),
+            "achievement_count": achievement_names.len().to_string(),
+            "achievement_names": achievement_names.join(","),
+            "click_action": "FLUTTER_NOTIFICATION_CLICK",
+        });
+        let saved = self.save(recipient_id, "achievement", &body, &data).await;
+        let pushed = self.push(recipient_id, "achievement", &session_id.to_string(), &title, &body, data).await;
+        info!(%recipient_id, session_id, saved, pushed, "achievement notification sent");
+        saved && pushed
+    }
+
+    async fn save(&self, recipient_id: Uuid, notification_type: &str, message: &str, data: &serde_json::Value) -> bool {
+        let row = json!({
+            "recipient_id": recipient_id,
+            "type": notification_type,
+            "message": message,
+            "data": data,
+            "is_read": false,
+            "created_at": Utc::now().to_rfc3339(),
+        });
+        match Query::table("notifications").insert(row).admin().execute(&self.supabase).await {
+            Ok(_) => true,
+            Err(e) => {
+                error!("Failed to save {} notification for {}: {}", notification_type, recipient_id, e);
+                false
+            }
+        }
+    }
+
+    async fn push(
+        &self,
+        recipient_id: Uuid,
+        notification_type: &str,
+        event_id: &str,
+        title: &str,
+        body: &str,
+        mut data: serde_json::Value,
+    ) -> bool {
+        let Some(fcm) = &self.fcm else {
+            return false;
+        };
+        let dedupe_key = format!("notif_sent:{}_{}", notification_type, event_id);
+        if cache_get(&self.cache, &dedupe_key).await.is_some() {
+            warn!("Duplicate notification skipped: {}", dedupe_key);
+            return true;
+        }
+
+        let tokens: Vec<String> = match Query::table("user_device_tokens")
+            .select("fcm_token")
+            .eq("user_id", recipient_id)
+            .eq("is_active", true)
+            .admin()
+            .execute(&self.supabase)
+            .await
+        {
+            Ok(response) => response.rows().iter().filter_map(|row| Some(row.get("fcm_token")?.as_str()?.to_string())).collect(),
+            Err(e) => {
+                error!("Failed to load device tokens for {}: {}", recipient_id, e);
+                return false;
+            }
+        };
+        if tokens.is_empty() {
+            info!("No device tokens for {}; notifications disabled", recipient_id);
+            return true;
+        }
+
+        let access_token = match fcm.access_token().await {
+            Ok(token) => token,
+            Err(e) => {
+                error!("Failed to get Firebase access token: {}", e);
+                return false;
+            }
+        };
+        data["type"] = json!(notification_type);
+        let mut sent = 0;
+        for token in &tokens {
+            match fcm.send(&access_token, token, title, body, &data).await {
+                Delivery::Sent => sent += 1,
+                Delivery::Unregistered => {
+                    warn!("Deactivating unregistered device token for {}", recipient_id);
+                    if let Err(e) = Query::table("user_device_tokens")
+                        .eq("fcm_token", token)
+                        .update(json!({ "is_active": false }))
+                        .admin()
+                        .execute(&self.supabase)
+                        .await
+                    {
+                        error!("Failed to deactivate device token: {}", e);
+                    }
+                }
+                Delivery::Failed => {}
+            }
+        }
+        info!(devices = tokens.len(), sent, "push notification delivered");
+        cache_set(&self.cache, &dedupe_key, &json!(true), NOTIFICATION_DEDUPE_TTL_S).await;
+        sent > 0
+    }
+}
+
+// Title and body the Python service uses for one or several new achievements
+fn achievement_message(names: &[String]) -> (String, String) {
+    match names {
+        [name] => ("🏆 Achievement Unlocked!".to_string(), format!("You earned: {}", name)),
+        _ => {
+            let mut body = format!("You earned: {}", names.iter().take(2).cloned().collect::<Vec<_>>().join(", "));
+            if names.len() > 2 {
+                body.push_str(&format!(" and {} more!", names.len() - 2));
+            }
+            (format!("🏆 {} Achievements Unlocked!", names.len()), body)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn message_matches_python_wording() {
+        assert_eq!(
+            achievement_message(&names(&["First Ruck"])),
+            ("🏆 Achievement Unlocked!".to_string(), "You earned: First Ruck".to_string())
+        );
+        assert_eq!(
+            achievement_message(&names(&["A", "B"])),
+            ("🏆 2 Achievements Unlocked!".to_string(), "You earned: A, B".to_string())
+        );
+        assert_eq!(achievement_message(&names(&["A", "B", "C", "D"])).1, "You earned: A, B and 2 more!");
+    }
+}
@@ -0,0 +1,108 @@
+use tracing::warn;
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::evaluation::{session_f64, value_f64};
+use crate::supabase::{Auth, Query, SupabaseConfig, SupabaseError};
+
+// Columns the power points formula reads
+pub const POWER_POINTS_INPUTS: &str = "id, ruck_weight_kg, distance_km, elevation_gain_m, power_points";
+
+// Same formula as the ruck_session.power_points generated column (fix_powerpoints_hike_calculation.sql):
+// weight × distance × elevation gain, with hikes counted as 1 kg and flat routes as 1 m of gain.
+// GREATEST ignores NULL in Postgres, so a missing weight also counts as 1 kg.
+pub fn calculate_power_points(ruck_weight_kg: Option<f64>, distance_km: Option<f64>, elevation_gain_m: Option<f64>) -> f64 {
+    ruck_weight_kg.unwrap_or(1.0).max(1.0) * distance_km.unwrap_or(0.0) * elevation_gain_m.unwrap_or(1.0).max(1.0)
+}
+
+pub fn computed_power_points(session: &HashMap<String, serde_json::Value>) -> f64 {
+    calculate_power_points(
+        session_f64(session, "ruck_weight_kg"),
+        session_f64(session, "distance_km"),
+        session_f64(session, "elevation_gain_m"),
+    )
+}
+
+// The stored value, or the formula when power_points is null or empty
+pub fn session_power_points(session: &HashMap<String, serde_json::Value>) -> f64 {
+    session_f64(session, "power_points").unwrap_or_else(|| computed_power_points(session))
+}
+
+fn sum_power_points(rows: &[serde_json::Value]) -> f64 {
+    rows.iter()
+        .filter_map(|row| serde_json::from_value::<HashMap<String, serde_json::Value>>(row.clone()).ok())
+        .map(|session| session_power_points(&session))
+        .sum()
+}
+
+// Lifetime power points over completed sessions. calculate_user_power_points skips sessions whose
+// power_points is null, so those are computed here and added; without the RPC every session is summed.
+pub async fn total_power_points(config: &SupabaseConfig, auth: Auth, user_id: Uuid) -> Result<f64, SupabaseError> {
+    let sessions = Query::table("ruck_session")
+        .select(POWER_POINTS_INPUTS)
+        .eq("user_id", user_id)
+        .eq("status", "completed")
+        .order("id", false)
+        .auth(auth.clone());
+
+    match Query::rpc("calculate_user_power_points", json!({ "user_id_param": user_id })).auth(auth).execute(config).await {
+        Ok(response) => {
+            let stored = value_f64(&response.data).unwrap_or(0.0);
+            let missing = sessions.is("power_points", "null").execute_all(config).await?;
+            Ok(stored + sum_power_points(&missing))
+        }
+        Err(e) => {
+            warn!("RPC calculate_user_power_points not available, using fallback: {}", e);
+            Ok(sum_power_points(&sessions.execute_all(config).await?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cases worked through the generated column:
+    // GREATEST(ruck_weight_kg, 1) * COALESCE(distance_km, 0) * GREATEST(COALESCE(elevation_gain_m, 1), 1)
+    #[test]
+    fn matches_the_generated_column() {
+        // (ruck_weight_kg, distance_km, elevation_gain_m, power_points)
+        let cases = [
+            (Some(20.0), Some(10.0), Some(100.0), 20_000.0),
+            // Hike: weight 0 counts as 1 kg
+            (Some(0.0), Some(5.0), Some(50.0), 250.0),
+            // GREATEST skips NULL, so a null weight is also 1 kg
+            (None, Some(5.0), Some(50.0), 250.0),
+            (Some(0.4), Some(5.0), Some(50.0), 250.0),
+            // Null distance is 0
+            (Some(20.0), None, Some(100.0), 0.0),
+            (Some(20.0), Some(0.0), Some(100.0), 0.0),
+            // Flat route: null, zero or negative gain counts as 1 m
+            (Some(20.0), Some(10.0), None, 200.0),
+            (Some(20.0), Some(10.0), Some(0.0), 200.0),
+            (Some(20.0), Some(10.0), Some(-3.0), 200.0),
+            (Some(20.0), Some(10.0), Some(0.5), 200.0),
+            (None, None, None, 0.0),
+            (None, Some(2.5), None, 2.5),
+        ];
+        for (weight, distance, elevation, expected) in cases {
+            let actual = calculate_power_points(weight, distance, elevation);
+            assert!((actual - expected).abs() < 1e-9, "{:?} {:?} {:?}: {} != {}", weight, distance, elevation, actual, expected);
+        }
+    }
+
+    #[test]
+    fn stored_value_wins_over_the_formula() {
+        let session = |value: serde_json::Value| -> HashMap<String, serde_json::Value> {
+            serde_json::from_value(json!({
+                "ruck_weight_kg": 20, "distance_km": 10, "elevation_gain_m": 100, "power_points": value,
+            }))
+            .unwrap()
+        };
+        assert_eq!(session_power_points(&session(json!("12.5"))), 12.5);
+        assert_eq!(session_power_points(&session(json!(7))), 7.0);
+        assert_eq!(session_power_points(&session(serde_json::Value::Null)), 20_000.0);
+        assert_eq!(session_power_points(&session(json!(""))), 20_000.0);
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use tracing::info;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::criteria::Criteria;
+use crate::models::{Achievement, KM_PER_MILE};
+use crate::stats::UserStats;
+use crate::supabase::{Query, SupabaseConfig, SupabaseError};
+
+// Where a user stands on one achievement, in the same unit as the criterion's target
+#[derive(Debug, Clone, PartialEq)]
+pub struct AchievementProgress {
+    pub achievement_id: i32,
+    pub current_value: f64,
+    pub target_value: f64,
+    pub percentage: f64,
+}
+
+impl AchievementProgress {
+    fn new(achievement_id: i32, current_value: f64, target_value: f64) -> AchievementProgress {
+        let percentage = if target_value > 0.0 {
+            (current_value / target_value * 100.0).clamp(0.0, 100.0)
+        } else {
+            100.0
+        };
+        AchievementProgress {
+            achievement_id,
+            current_value,
+            target_value,
+            percentage: (percentage * 10.0).round() / 10.0,
+        }
+    }
+}
+
+// Current value and target for criteria with a meaningful running total; None otherwise
+fn measure(achievement: &Achievement, stats: &UserStats, now: DateTime<Utc>) -> Option<(f64, f64)> {
+    let is_standard = achievement.unit_preference.as_deref() == Some("standard");
+    // Distances are summed in km; monthly/quarterly targets are in miles for standard achievements
+    let in_unit = |km: f64| if is_standard { km / KM_PER_MILE } else { km };
+    let count = |n: u64, target: u32| (n as f64, f64::from(target));
+
+    let measured = match &achievement.criteria {
+        Criteria::CumulativeDistance { target } => (stats.total_distance(now), *target),
+        Criteria::PowerPoints { target } => (stats.total_power_points(now), *target),
+        Criteria::DailyStreak { target, grace_days } => {
+            (f64::from(stats.streaks(*grace_days, now).daily.current), f64::from(*target))
+        }
+        Criteria::WeeklyStreak { target, grace_days } => {
+            (f64::from(stats.streaks(*grace_days, now).weekly.current), f64::from(*target))
+        }
+        Criteria::WeekendStreak { target, grace_days } => {
+            (f64::from(stats.streaks(*grace_days, now).weekend.current), f64::from(*target))
+        }
+        Criteria::PhotoUploads { target } => count(stats.photos_at(now), *target),
+        Criteria::WeatherVariety { target } => count(stats.weather_buckets(now).len() as u64, *target),
+        Criteria::TotalLikesGiven { target } => count(stats.likes_given_at(now), *target),
+        Criteria::TotalLikesReceived { target } => count(stats.likes_received_at(now), *target),
+        Criteria::MonthlyDistance { target } => (in_unit(stats.monthly_distance(now)), *target),
+        Criteria::QuarterlyDistance { target } => (in_unit(stats.quarterly_distance(now)), *target),
+        _ => return None,
+    };
+    Some(measured)
+}
+
+// Progress toward every measurable achievement in `unearned`
+pub fn compute_progress(unearned: &[&Achievement], stats: &UserStats) -> Vec<AchievementProgress> {
+    let now = Utc::now();
+    unearned
+        .iter()
+        .filter_map(|achievement| {
+            let (current, target) = measure(achievement, stats, now)?;
+            Some(AchievementProgress::new(achievement.id, current, target))
+        })
+        .collect()
+}
+
+// Compute and upsert achievement_progress rows (unique on user_id, achievement_id)
+pub async fn update_achievement_progress(
+    config: &SupabaseConfig,
+    user_id: Uuid,
+    unearned: &[&Achievement],
+    stats: &UserStats,
+) -> Result<usize, SupabaseError> {
+    let progress = compute_progress(unearned, stats);
+    if progress.is_empty() {
+        return Ok(0);
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let rows: Vec<serde_json::Value> = progress
+        .iter()
+        .map(|p| {
+            json!({
+                "user_id": user_id,
+                "achievement_id": p.achievement_id,
+                "current_value": p.current_value,
+                "target_value": p.target_value,
+                "last_updated": now,
+                "metadata": { "percentage": p.percentage },
+            })
+        })
+        .collect();
+    Query::table("achievement_progress")
+        .upsert(serde_json::Value::Array(rows))
+        .on_conflict("user_id,achievement_id")
+        .admin()
+        .execute(config)
+        .await?;
+    info!("Updated progress for {} achievements for user {}", progress.len(), user_id);
+    Ok(progress.len())
+}
@@ -0,0 +1,195 @@
+use chrono::Utc;
+use tracing::{error, info, instrument, warn};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::evaluation::{explain_criteria, meets_minimum_session, parse_timestamp, ConditionResult, SessionMinimums};
+use crate::models::{parse_achievements, Achievement};
+use crate::stats::UserStats;
+use crate::supabase::{Auth, Query, SupabaseConfig, SupabaseError};
+
+type Session = HashMap<String, serde_json::Value>;
+
+// An award taken back because the session data it relied on changed
+#[derive(Serialize, Clone, Debug)]
+pub struct Revocation {
+    pub user_achievement_id: i64,
+    pub achievement_id: i32,
+    pub achievement_key: String,
+    pub awarded_for_session: Option<i64>,
+    pub reason: String,
+}
+
+async fn load_session(config: &SupabaseConfig, auth: Auth, session_id: i64) -> Result<Option<Session>, SupabaseError> {
+    let response = Query::table("ruck_session").select("*").eq("id", session_id).auth(auth).execute(config).await?;
+    Ok(response.first().and_then(|row| serde_json::from_value(row.clone()).ok()))
+}
+
+// The caller's own session as it stands now, or None once it has been deleted. A session the
+// caller can't see but that still exists isn't theirs, so that is a 404 rather than a deletion.
+async fn changed_session(
+    config: &SupabaseConfig,
+    auth: Auth,
+    user_id: Uuid,
+    session_id: i64,
+) -> Result<Option<Session>, AppError> {
+    let owner = |session: &Session| session.get("user_id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok());
+    if let Some(session) = load_session(config, auth, session_id).await? {
+        if owner(&session) == Some(user_id) {
+            return Ok(Some(session));
+        }
+    } else if Query::table("ruck_session").select("id").eq("id", session_id).admin().execute(config).await?.rows().is_empty() {
+        return Ok(None);
+    }
+    warn!("Session {} not found for user {}", session_id, user_id);
+    Err(AppError::NotFound("Session not found"))
+}
+
+async fn latest_completed_session(config: &SupabaseConfig, user_id: Uuid) -> Result<Option<Session>, SupabaseError> {
+    let response = Query::table("ruck_session")
+        .select("*")
+        .eq("user_id", user_id)
+        .eq("status", "completed")
+        .order("started_at", true)
+        .limit(1)
+        .admin()
+        .execute(config)
+        .await?;
+    Ok(response.first().and_then(|row| serde_json::from_value(row.clone()).ok()))
+}
+
+fn started_at(session: &Session) -> Option<chrono::DateTime<Utc>> {
+    session.get("started_at").and_then(|v| v.as_str()).and_then(parse_timestamp)
+}
+
+// First failing leaf of an explanation, as "condition: observed X, target Y"
+fn failure_reason(result: &ConditionResult) -> String {
+    match result.children.iter().find(|child| !child.passed) {
+        Some(child) => failure_reason(child),
+        None => format!("{} no longer met: observed {}, target {}", result.condition, result.observed, result.target),
+    }
+}
+
+// Re-check the caller's awards that depended on `session_id` after it was edited or deleted:
+// awards made by that session, and history-based awards (totals, streaks, counts) made by it or
+// any later session. Each is re-evaluated against the session that earned it as it stands now and
+// revoked if it no longer holds; the revoke_user_achievement RPC moves the award row to
+// achievement_revocations, with the reason in its metadata, in one statement. Awards made by a
+// deleted session are already gone through the ON DELETE CASCADE on user_achievements.session_id,
+// so for a deletion only the history-based ones are re-checked.
+#[instrument(skip(config, auth))]
+pub async fn reevaluate_session_awards(
+    config: &SupabaseConfig,
+    auth: Auth,
+    user_id: Uuid,
+    session_id: i64,
+    minimums: SessionMinimums,
+) -> Result<Vec<Revocation>, AppError> {
+    let changed = changed_session(config, auth, user_id, session_id).await?;
+    let event = if changed.is_some() { "updated" } else { "deleted" };
+    let changed_start = changed.as_ref().and_then(started_at);
+
+    let awards = Query::table("user_achievements")
+        .select("id, achievement_id, session_id, earned_at, metadata")
+        .eq("user_id", user_id)
+        .admin()
+        .execute(config)
+        .await?;
+    if awards.rows().is_empty() {
+        return Ok(Vec::new());
+    }
+    let achievement_ids: Vec<i64> = awards.rows().iter().filter_map(|row| row.get("achievement_id")?.as_i64()).collect();
+    let achievements_response = Query::table("achievements").select("*").in_("id", achievement_ids).admin().execute(config).await?;
+    let (achievements, errors) = parse_achievements(achievements_response.rows());
+    for e in &errors {
+        error!("{}", e);
+    }
+    let achievements: HashMap<i32, Achievement> = achievements.into_iter().map(|a| (a.id, a)).collect();
+    // History as it stands after the change
+    let mut stats = UserStats::load(config, user_id).await?;
+
+    let mut sessions: HashMap<i64, Option<Session>> = HashMap::new();
+    let mut revocations = Vec::new();
+    for row in awards.rows() {
+        let (Some(id), Some(achievement)) = (
+            row.get("id").and_then(|v| v.as_i64()),
+            row.get("achievement_id").and_then(|v| v.as_i64()).and_then(|id| achievements.get(&(id as i32))),
+        ) else {
+            continue;
+        };
+        let awarded_for = row.get("session_id").and_then(|v| v.as_i64());
+
+        let earning_session = match awarded_for {
+            Some(sid) if sid == session_id => changed.clone(),
+            Some(sid) => match sessions.entry(sid) {
+                Entry::Occupied(entry) => entry.get().clone(),
+                Entry::Vacant(entry) => entry.insert(load_session(config, Auth::Admin, sid).await?).clone(),
+            },
+            None => None,
+        };
+        // Session-scoped rules only depend on the session that earned them
+        let depends_on_change = if awarded_for == Some(session_id) {
+            true
+        } else if achievement.criteria.is_session_scoped() {
+            false
+        } else {
+            match (changed_start, earning_session.as_ref().and_then(started_at)) {
+                (Some(changed), Some(earned)) => earned >= changed,
+                _ => true,
+            }
+        };
+        if !depends_on_change {
+            continue;
+        }
+
+        // Without its session (deleted, or never recorded) the award is judged on the latest history
+        let session = match earning_session {
+            Some(session) => Some(session),
+            None => latest_completed_session(config, user_id).await?,
+        };
+        let reason = match session {
+            None => Some("no completed sessions remain".to_string()),
+            Some(session) if !meets_minimum_session(&session, minimums) => {
+                Some("session no longer meets the minimum duration and distance".to_string())
+            }
+            Some(session) => {
+                if let Some(id) = session.get("id").and_then(|v| v.as_i64()) {
+                    stats.load_splits(config, &[id]).await?;
+                }
+                let explanation = explain_criteria(&session, achievement, &stats, minimums, false);
+                (!explanation.passed).then(|| failure_reason(&explanation))
+            }
+        };
+        let Some(reason) = reason else {
+            continue;
+        };
+
+        let mut metadata = row.get("metadata").cloned().filter(|m| m.is_object()).unwrap_or_else(|| json!({}));
+        metadata["revocation_reason"] = json!(reason);
+        metadata["revoked_after_session"] = json!({ "session_id": session_id, "event": event });
+        let revoked = Query::rpc("revoke_user_achievement", json!({ "p_user_achievement_id": id, "p_metadata": metadata }))
+            .admin()
+            .execute(config)
+            .await?;
+        // A concurrent re-evaluation got there first
+        if revoked.data != json!(true) {
+            continue;
+        }
+        info!(
+            "Revoked {} from user {} after session {} was {}: {}",
+            achievement.achievement_key, user_id, session_id, event, reason
+        );
+        revocations.push(Revocation {
+            user_achievement_id: id,
+            achievement_id: achievement.id,
+            achievement_key: achievement.achievement_key.clone(),
+            awarded_for_session: awarded_for,
+            reason,
+        });
+    }
+    Ok(revocations)
+}
@@ -7,6 +7,8 @@ from decimal import Decimal, InvalidOperation
 from datetime import datetime, timezone, timedelta
 import json
 import math
+import threading
+import requests
 from typing import Optional, Union
 from ..supabase_client import get_supabase_client, get_supabase_admin_client
 from ..services.redis_cache_service import cache_get, cache_set, cache_delete_pattern
@@ -18,6 +20,40 @@ from .user_coaching_plans import _record_session_against_plan
 
 logger = logging.getLogger(__name__)
 
+# Base URL of the achievements service (e.g. https://achievements.example.com); unset disables re-evaluation
+ACHIEVEMENTS_API_URL = os.environ.get('ACHIEVEMENTS_API_URL', '').rstrip('/')
+
+
+def _reevaluate_session_achievements(ruck_id):
+    """Ask the achievements service to re-check awards that depended on an edited or deleted session.
+
+    Runs in the background with the caller's token so a slow or unavailable service never fails the edit.
+    """
+    if not ACHIEVEMENTS_API_URL:
+        logger.debug(f"[ACHIEVEMENTS] ACHIEVEMENTS_API_URL not set; skipping re-evaluation for session {ruck_id}")
+        return
+    access_token = getattr(g, 'access_token', None)
+    if not access_token:
+        logger.warning(f"[ACHIEVEMENTS] No access token; skipping re-evaluation for session {ruck_id}")
+        return
+
+    def _post():
+        try:
+            resp = requests.post(
+                f"{ACHIEVEMENTS_API_URL}/achievements/reevaluate/{ruck_id}",
+                headers={'Authorization': f'Bearer {access_token}'},
+                timeout=30,
+            )
+            if resp.status_code != 200:
+                logger.error(f"[ACHIEVEMENTS] Re-evaluation for session {ruck_id} failed with {resp.status_code}: {resp.text[:200]}")
+            else:
+                revoked = resp.json().get('revoked') or []
+                logger.info(f"[ACHIEVEMENTS] Re-evaluated session {ruck_id}: {len(revoked)} revoked")
+        except Exception as e:
+            logger.error(f"[ACHIEVEMENTS] Re-evaluation request for session {ruck_id} failed: {e}")
+
+    threading.Thread(target=_post, daemon=True).start()
+
 def validate_ruck_id(ruck_id):
     """Coerce a path parameter ruck_id into an int, or return None if invalid."""
     try:
@@ -310,6 +346,9 @@ class RuckSessionResource(Resource):
             except Exception:
                 pass
 
+            # Totals and streaks the session counted toward may no longer hold
+            _reevaluate_session_achievements(ruck_id)
+
             return {'message': 'Session deleted', 'id': ruck_id}, 200
         except Exception as e:
             logger.error(f"Error deleting session {ruck_id}: {e}")
@@ -763,6 +802,8 @@ class RuckSessionDetailResource(Resource):
             except Exception:
                 pass
 
+            _reevaluate_session_achievements(ruck_id)
+
             return update_resp.data[0], 200
         except Exception as e:
             logger.error(f"Error patching session {ruck_id}: {e}")
@@ -2486,6 +2527,9 @@ class RuckSessionEditResource(Resource):
             cache_delete_pattern(f"ruck_session:{g.user.id}:*")
             cache_delete_pattern(f"location_points:{ruck_id}:*")
             cache_delete_pattern(f"session_details:{ruck_id}:*")
+
+            # The trimmed distance, duration and pace may no longer earn the session's awards
+            _reevaluate_session_achievements(ruck_id)
             
             logger.info(f"Successfully completed session {ruck_id}")
             
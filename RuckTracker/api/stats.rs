@@ -0,0 +1,297 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+use tracing::info;
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::evaluation::{parse_timestamp, session_f64, value_f64, SessionMinimums};
+use crate::power_points::session_power_points;
+use crate::streaks::{resolve_timezone, streaks_at, UserStreaks};
+use crate::supabase::{Auth, Query, SupabaseConfig, SupabaseError};
+use crate::weather::{classify, parse_weather, WeatherBucket};
+
+// Session ids per `in.(...)` filter, keeping request URLs a sane length
+const IDS_PER_QUERY: usize = 100;
+
+// A completed session as the history-based criteria see it
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub started_at: DateTime<Utc>,
+    pub distance_km: f64,
+    pub duration_seconds: f64,
+    pub power_points: f64,
+    pub weather: BTreeSet<WeatherBucket>,
+}
+
+// Photos or likes: the exact total, as achievements.py counted them for a live check, or every
+// timestamp when a replay needs the count as it stood at each session
+#[derive(Debug, Clone)]
+pub enum ActivityCount {
+    Total(u64),
+    Timestamps(Vec<DateTime<Utc>>),
+}
+
+impl Default for ActivityCount {
+    fn default() -> ActivityCount {
+        ActivityCount::Total(0)
+    }
+}
+
+impl ActivityCount {
+    // A total has no dates, so it stands for every `as_of`
+    pub fn at(&self, as_of: DateTime<Utc>) -> u64 {
+        match self {
+            ActivityCount::Total(total) => *total,
+            ActivityCount::Timestamps(timestamps) => timestamps.iter().filter(|t| **t <= as_of).count() as u64,
+        }
+    }
+
+    fn total(&self) -> u64 {
+        match self {
+            ActivityCount::Total(total) => *total,
+            ActivityCount::Timestamps(timestamps) => timestamps.len() as u64,
+        }
+    }
+}
+
+// `query` filtered to one user's rows, counted with count=exact, or with `history` downloaded so
+// each row's created_at is known. `columns` may embed a resource the filter needs.
+async fn load_activity(config: &SupabaseConfig, query: Query, columns: &str, history: bool) -> Result<ActivityCount, SupabaseError> {
+    if history {
+        let rows = query
+            .select(&format!("{}, created_at", columns))
+            .order("id", false)
+            .admin()
+            .execute_all(config)
+            .await?;
+        return Ok(ActivityCount::Timestamps(timestamps(&rows, "created_at")));
+    }
+    let response = query.select(columns).count_exact().limit(1).admin().execute(config).await?;
+    Ok(ActivityCount::Total(response.count.unwrap_or(response.rows().len() as u64)))
+}
+
+// Everything check_criteria needs about a user, read once per check with a fixed set of queries.
+// Session history is kept whole and cut off at each evaluation's `as_of`, so one load serves a
+// live check and a backfill replay alike; only a replay needs photo and like history too.
+#[derive(Debug, Clone)]
+pub struct UserStats {
+    pub timezone: Tz,
+    // Completed sessions, oldest first
+    pub sessions: Vec<SessionSummary>,
+    pub photos: ActivityCount,
+    pub likes_given: ActivityCount,
+    pub likes_received: ActivityCount,
+    // (distance_km, duration_seconds) per split in split order, for sessions passed to `load_splits`
+    pub splits: HashMap<i64, Vec<(f64, f64)>>,
+}
+
+fn timestamps(rows: &[serde_json::Value], column: &str) -> Vec<DateTime<Utc>> {
+    rows.iter().filter_map(|row| parse_timestamp(row.get(column)?.as_str()?)).collect()
+}
+
+fn first_of_month(year: i32, month: u32) -> DateTime<Utc> {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .expect("valid month")
+        .and_hms_opt(0, 0, 0)
+        .expect("valid time")
+        .and_utc()
+}
+
+impl UserStats {
+    // Photo and like totals, for a check made now
+    pub async fn load(config: &SupabaseConfig, user_id: Uuid) -> Result<UserStats, SupabaseError> {
+        UserStats::load_with(config, user_id, false).await
+    }
+
+    // Photo and like timestamps as well, for replaying past sessions
+    pub async fn load_history(config: &SupabaseConfig, user_id: Uuid) -> Result<UserStats, SupabaseError> {
+        UserStats::load_with(config, user_id, true).await
+    }
+
+    async fn load_with(config: &SupabaseConfig, user_id: Uuid, history: bool) -> Result<UserStats, SupabaseError> {
+        let timezone = resolve_timezone(config, Auth::Admin, user_id).await;
+
+        let session_rows = Query::table("ruck_session")
+            .select("id, started_at, distance_km, duration_seconds, ruck_weight_kg, elevation_gain_m, power_points, weather_conditions")
+            .eq("user_id", user_id)
+            .eq("status", "completed")
+            .order("id", false)
+            .admin()
+            .execute_all(config)
+            .await?;
+        let mut sessions = Vec::new();
+        for row in &session_rows {
+            let session: HashMap<String, serde_json::Value> = match serde_json::from_value(row.clone()) {
+                Ok(session) => session,
+                Err(_) => continue,
+            };
+            let Some(started_at) = session.get("started_at").and_then(|v| v.as_str()).and_then(parse_timestamp) else {
+                continue;
+            };
+            sessions.push(SessionSummary {
+                started_at,
+                distance_km: session_f64(&session, "distance_km").unwrap_or(0.0),
+                duration_seconds: session_f64(&session, "duration_seconds").unwrap_or(0.0),
+                power_points: session_power_points(&session),
+                weather: session.get("weather_conditions").and_then(parse_weather).map(|w| classify(&w)).unwrap_or_default(),
+            });
+        }
+        sessions.sort_by_key(|s| s.started_at);
+
+        let photos = load_activity(config, Query::table("ruck_photos").eq("user_id", user_id), "id", history).await?;
+        let likes_given = load_activity(config, Query::table("ruck_likes").eq("user_id", user_id), "id", history).await?;
+        // Likes on any of the user's rucks, filtered through the embedded session
+        let likes_received = load_activity(
+            config,
+            Query::table("ruck_likes").eq("ruck_session.user_id", user_id),
+            "id, ruck_session!inner(user_id)",
+            history,
+        )
+        .await?;
+
+        info!(
+            "Loaded stats for user {}: sessions={} photos={} likes_given={} likes_received={} tz={}",
+            user_id,
+            sessions.len(),
+            photos.total(),
+            likes_given.total(),
+            likes_received.total(),
+            timezone.name()
+        );
+        Ok(UserStats { timezone, sessions, photos, likes_given, likes_received, splits: HashMap::new() })
+    }
+
+    // Splits are only needed for the sessions being checked, so they're loaded separately
+    pub async fn load_splits(&mut self, config: &SupabaseConfig, session_ids: &[i64]) -> Result<(), SupabaseError> {
+        let missing: Vec<i64> = session_ids.iter().copied().filter(|id| !self.splits.contains_key(id)).collect();
+        for ids in missing.chunks(IDS_PER_QUERY) {
+            let rows = Query::table("session_splits")
+                .select("session_id, split_number, split_distance_km, split_duration_seconds")
+                .in_("session_id", ids)
+                .order("session_id", false)
+                .order("split_number", false)
+                .admin()
+                .execute_all(config)
+                .await?;
+            for id in ids {
+                self.splits.entry(*id).or_default();
+            }
+            for row in &rows {
+                let (Some(session_id), Some(distance), Some(duration)) = (
+                    row.get("session_id").and_then(|v| v.as_i64()),
+                    row.get("split_distance_km").and_then(value_f64),
+                    row.get("split_duration_seconds").and_then(value_f64),
+                ) else {
+                    continue;
+                };
+                if distance > 0.0 && duration > 0.0 {
+                    self.splits.entry(session_id).or_default().push((distance, duration));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Completed sessions started up to `as_of`
+    pub fn history(&self, as_of: DateTime<Utc>) -> impl Iterator<Item = &SessionSummary> + '_ {
+        self.sessions.iter().take_while(move |s| s.started_at <= as_of)
+    }
+
+    pub fn total_distance(&self, as_of: DateTime<Utc>) -> f64 {
+        self.history(as_of).map(|s| s.distance_km).sum()
+    }
+
+    pub fn total_power_points(&self, as_of: DateTime<Utc>) -> f64 {
+        self.history(as_of).map(|s| s.power_points).sum()
+    }
+
+    pub fn session_starts(&self, as_of: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        self.history(as_of).map(|s| s.started_at).collect()
+    }
+
+    pub fn streaks(&self, grace: u32, as_of: DateTime<Utc>) -> UserStreaks {
+        streaks_at(&self.session_starts(as_of), self.timezone, grace, as_of)
+    }
+
+    // Sessions started in [start, end] of at least the given duration and distance
+    pub fn sessions_between(&self, start: DateTime<Utc>, end: DateTime<Utc>, min_duration_s: f64, min_distance_km: f64) -> u64 {
+        self.history(end)
+            .filter(|s| s.started_at >= start)
+            .filter(|s| s.duration_seconds >= min_duration_s && s.distance_km >= min_distance_km)
+            .count() as u64
+    }
+
+    // Sessions up to `at` that clear the global minimums
+    pub fn qualifying_sessions(&self, at: DateTime<Utc>, minimums: SessionMinimums) -> u64 {
+        self.sessions_between(DateTime::<Utc>::MIN_UTC, at, minimums.duration_s, minimums.distance_km)
+    }
+
+    pub fn photos_at(&self, as_of: DateTime<Utc>) -> u64 {
+        self.photos.at(as_of)
+    }
+
+    pub fn likes_given_at(&self, as_of: DateTime<Utc>) -> u64 {
+        self.likes_given.at(as_of)
+    }
+
+    pub fn likes_received_at(&self, as_of: DateTime<Utc>) -> u64 {
+        self.likes_received.at(as_of)
+    }
+
+    pub fn weather_buckets(&self, as_of: DateTime<Utc>) -> BTreeSet<WeatherBucket> {
+        self.history(as_of).flat_map(|s| s.weather.iter().copied()).collect()
+    }
+
+    fn distance_since(&self, start: DateTime<Utc>, as_of: DateTime<Utc>) -> f64 {
+        self.history(as_of).filter(|s| s.started_at >= start).map(|s| s.distance_km).sum()
+    }
+
+    // Calendar month / quarter containing `as_of` (UTC, like get_user_monthly_distance), up to `as_of`
+    pub fn monthly_distance(&self, as_of: DateTime<Utc>) -> f64 {
+        self.distance_since(first_of_month(as_of.year(), as_of.month()), as_of)
+    }
+
+    pub fn quarterly_distance(&self, as_of: DateTime<Utc>) -> f64 {
+        let quarter_start_month = (as_of.month() - 1) / 3 * 3 + 1;
+        self.distance_since(first_of_month(as_of.year(), quarter_start_month), as_of)
+    }
+
+    // Months in a row, counting back from the one containing `as_of`, with at least `min_rucks`
+    // rucks; stops looking once `target_months` is reached
+    pub fn consecutive_active_months(&self, target_months: u32, min_rucks: u32, as_of: DateTime<Utc>) -> u32 {
+        let mut month_counts: HashMap<(i32, u32), u32> = HashMap::new();
+        for session in self.history(as_of) {
+            *month_counts.entry((session.started_at.year(), session.started_at.month())).or_insert(0) += 1;
+        }
+
+        let (mut year, mut month) = (as_of.year(), as_of.month());
+        let mut consecutive_months = 0;
+        for _ in 0..target_months {
+            if month_counts.get(&(year, month)).copied().unwrap_or(0) >= min_rucks {
+                consecutive_months += 1;
+            } else {
+                break;
+            }
+            (year, month) = if month == 1 { (year - 1, 12) } else { (year, month - 1) };
+        }
+        consecutive_months
+    }
+
+    // Coefficient of variation of pace across the 10 most recent sessions; None with fewer than 3
+    pub fn pace_variation(&self, as_of: DateTime<Utc>) -> Option<f64> {
+        let recent: Vec<&SessionSummary> = self.history(as_of).collect();
+        let paces: Vec<f64> = recent
+            .iter()
+            .rev()
+            .take(10)
+            .filter(|s| s.duration_seconds > 0.0 && s.distance_km > 0.0)
+            .map(|s| s.duration_seconds / s.distance_km)
+            .collect();
+        if paces.len() < 3 {
+            return None;
+        }
+        let mean = paces.iter().sum::<f64>() / paces.len() as f64;
+        let variance = paces.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / paces.len() as f64;
+        Some(if mean > 0.0 { variance.sqrt() / mean } else { 1.0 })
+    }
+}
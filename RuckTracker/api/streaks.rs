@@ -0,0 +1,244 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use tracing::warn;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+use crate::evaluation::parse_timestamp;
+use crate::supabase::{Auth, Query, SupabaseConfig, SupabaseError};
+
+// Current and longest run for one kind of streak; `last_active` is the start of the most
+// recent active period (the day, the ISO week's Monday, or the weekend's Saturday)
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct StreakState {
+    pub current: u32,
+    pub longest: u32,
+    pub last_active: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UserStreaks {
+    pub timezone: String,
+    pub today: NaiveDate,
+    pub grace_days: u32,
+    pub daily: StreakState,
+    pub weekly: StreakState,
+    pub weekend: StreakState,
+}
+
+// Timezone name in `column` of the first row, if it is a valid IANA identifier
+async fn query_timezone(config: &SupabaseConfig, query: Query, column: &str) -> Option<Tz> {
+    let response = match query.limit(1).execute(config).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Could not read {}: {}", column, e);
+            return None;
+        }
+    };
+    let name = response.first()?.get(column)?.as_str()?.to_string();
+    match name.parse::<Tz>() {
+        Ok(tz) => Some(tz),
+        Err(_) => {
+            warn!("Ignoring unknown timezone '{}' in {}", name, column);
+            None
+        }
+    }
+}
+
+// user.timezone (set through the profile update in auth.py), else the most recent coaching plan's notification timezone, else UTC.
+// Lookup failures (e.g. the column not migrated yet) fall through rather than failing the caller.
+pub async fn resolve_timezone(config: &SupabaseConfig, auth: Auth, user_id: Uuid) -> Tz {
+    let user_query = Query::table("user").select("timezone").eq("id", user_id).auth(auth.clone());
+    if let Some(tz) = query_timezone(config, user_query, "timezone").await {
+        return tz;
+    }
+    let plan_query = Query::table("user_coaching_plans")
+        .select("plan_notification_timezone")
+        .eq("user_id", user_id)
+        .order("created_at", true)
+        .auth(auth);
+    query_timezone(config, plan_query, "plan_notification_timezone").await.unwrap_or(Tz::UTC)
+}
+
+pub async fn completed_session_starts(
+    config: &SupabaseConfig,
+    auth: Auth,
+    user_id: Uuid,
+) -> Result<Vec<DateTime<Utc>>, SupabaseError> {
+    let rows = Query::table("ruck_session")
+        .select("started_at")
+        .eq("user_id", user_id)
+        .eq("status", "completed")
+        .order("started_at", true)
+        .order("id", true)
+        .auth(auth)
+        .execute_all(config)
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| row.get("started_at").and_then(|v| v.as_str()).and_then(parse_timestamp))
+        .collect())
+}
+
+// Calendar days on which the user rucked, in their timezone
+pub fn local_days(starts: &[DateTime<Utc>], tz: Tz) -> BTreeSet<NaiveDate> {
+    starts.iter().map(|start| start.with_timezone(&tz).date_naive()).collect()
+}
+
+fn iso_week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(i64::from(day.weekday().num_days_from_monday()))
+}
+
+fn is_weekend(day: NaiveDate) -> bool {
+    day.weekday().num_days_from_monday() >= 5
+}
+
+// Saturday of the weekend `day` falls in; weekdays map to the upcoming weekend
+fn weekend_start(day: NaiveDate) -> NaiveDate {
+    let weekday = i64::from(day.weekday().num_days_from_monday());
+    if weekday >= 5 { day - Duration::days(weekday - 5) } else { day + Duration::days(5 - weekday) }
+}
+
+// Walk active periods oldest to newest. Consecutive periods extend a run, and so does a gap of
+// up to `grace` missed periods (missed periods don't count toward the length). The current run
+// is still alive while the period in progress hasn't been rucked yet, so a daily streak isn't
+// lost at midnight before the user has had a chance to go out.
+fn streak_state(periods: &BTreeSet<NaiveDate>, current_period: NaiveDate, period_days: i64, grace: u32) -> StreakState {
+    let max_step = 1 + i64::from(grace);
+    let mut run = 0;
+    let mut longest = 0;
+    let mut last: Option<NaiveDate> = None;
+    for &period in periods.range(..=current_period) {
+        run = match last {
+            Some(prev) if (period - prev).num_days() / period_days <= max_step => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        last = Some(period);
+    }
+
+    let current = match last {
+        Some(prev) if (current_period - prev).num_days() / period_days <= max_step => run,
+        _ => 0,
+    };
+    StreakState { current, longest, last_active: last }
+}
+
+pub fn daily_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate, grace: u32) -> StreakState {
+    streak_state(days, today, 1, grace)
+}
+
+pub fn weekly_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate, grace: u32) -> StreakState {
+    let weeks = days.iter().copied().map(iso_week_start).collect();
+    streak_state(&weeks, iso_week_start(today), 7, grace)
+}
+
+pub fn weekend_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate, grace: u32) -> StreakState {
+    let weekends = days.iter().copied().filter(|d| is_weekend(*d)).map(weekend_start).collect();
+    streak_state(&weekends, weekend_start(today), 7, grace)
+}
+
+// All three streaks as they stood at `as_of`, bucketed by the user's local calendar
+pub fn streaks_at(starts: &[DateTime<Utc>], tz: Tz, grace: u32, as_of: DateTime<Utc>) -> UserStreaks {
+    let starts: Vec<DateTime<Utc>> = starts.iter().copied().filter(|start| *start <= as_of).collect();
+    let days = local_days(&starts, tz);
+    let today = as_of.with_timezone(&tz).date_naive();
+    UserStreaks {
+        timezone: tz.name().to_string(),
+        today,
+        grace_days: grace,
+        daily: daily_streak(&days, today, grace),
+        weekly: weekly_streak(&days, today, grace),
+        weekend: weekend_streak(&days, today, grace),
+    }
+}
+
+pub async fn load_user_streaks(
+    config: &SupabaseConfig,
+    auth: Auth,
+    user_id: Uuid,
+    grace: u32,
+    as_of: DateTime<Utc>,
+) -> Result<UserStreaks, SupabaseError> {
+    let tz = resolve_timezone(config, auth.clone(), user_id).await;
+    let starts = completed_session_starts(config, auth, user_id).await?;
+    Ok(streaks_at(&starts, tz, grace, as_of))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn days(list: &[NaiveDate]) -> BTreeSet<NaiveDate> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn consecutive_days_form_a_streak() {
+        let active = days(&[date(2026, 3, 1), date(2026, 3, 2), date(2026, 3, 3)]);
+        let state = daily_streak(&active, date(2026, 3, 3), 0);
+        assert_eq!(state, StreakState { current: 3, longest: 3, last_active: Some(date(2026, 3, 3)) });
+    }
+
+    #[test]
+    fn daily_streak_survives_until_today_is_over() {
+        let active = days(&[date(2026, 3, 1), date(2026, 3, 2)]);
+        assert_eq!(daily_streak(&active, date(2026, 3, 3), 0).current, 2);
+        assert_eq!(daily_streak(&active, date(2026, 3, 4), 0).current, 0);
+        assert_eq!(daily_streak(&active, date(2026, 3, 4), 0).longest, 2);
+    }
+
+    #[test]
+    fn grace_bridges_missed_days_without_counting_them() {
+        let active = days(&[date(2026, 3, 1), date(2026, 3, 3), date(2026, 3, 4)]);
+        assert_eq!(daily_streak(&active, date(2026, 3, 4), 0).current, 2);
+        assert_eq!(daily_streak(&active, date(2026, 3, 4), 1).current, 3);
+        // Two missed days is more than one grace day allows
+        let active = days(&[date(2026, 3, 1), date(2026, 3, 4)]);
+        assert_eq!(daily_streak(&active, date(2026, 3, 4), 1).current, 1);
+        assert_eq!(daily_streak(&active, date(2026, 3, 4), 2).current, 2);
+    }
+
+    #[test]
+    fn weekly_streak_counts_iso_weeks() {
+        // Sunday 2026-03-08 and Monday 2026-03-09 are in consecutive ISO weeks
+        let active = days(&[date(2026, 3, 2), date(2026, 3, 8), date(2026, 3, 9)]);
+        let state = weekly_streak(&active, date(2026, 3, 12), 0);
+        assert_eq!(state.current, 2);
+        assert_eq!(state.last_active, Some(date(2026, 3, 9)));
+        // A skipped week breaks it unless one week of grace is allowed
+        assert_eq!(weekly_streak(&active, date(2026, 3, 23), 0).current, 0);
+        assert_eq!(weekly_streak(&active, date(2026, 3, 23), 1).current, 2);
+    }
+
+    #[test]
+    fn weekend_streak_ignores_weekdays() {
+        // Sat 3/7, Sun 3/15 and a Wednesday in between
+        let active = days(&[date(2026, 3, 7), date(2026, 3, 11), date(2026, 3, 15)]);
+        let state = weekend_streak(&active, date(2026, 3, 18), 0);
+        assert_eq!(state.current, 2);
+        assert_eq!(state.last_active, Some(date(2026, 3, 14)));
+    }
+
+    #[test]
+    fn days_are_bucketed_in_the_users_timezone() {
+        // 03:30 UTC on the 2nd is still the evening of the 1st in Denver
+        let start = DateTime::parse_from_rfc3339("2026-03-02T03:30:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(local_days(&[start], Tz::UTC), days(&[date(2026, 3, 2)]));
+        assert_eq!(local_days(&[start], chrono_tz::America::Denver), days(&[date(2026, 3, 1)]));
+    }
+
+    #[test]
+    fn streaks_at_ignores_sessions_after_as_of() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let starts = [at("2026-03-01T12:00:00Z"), at("2026-03-02T12:00:00Z"), at("2026-03-03T12:00:00Z")];
+        let streaks = streaks_at(&starts, Tz::UTC, 0, at("2026-03-02T20:00:00Z"));
+        assert_eq!(streaks.today, date(2026, 3, 2));
+        assert_eq!(streaks.daily.current, 2);
+    }
+}
@@ -0,0 +1,343 @@
+use tracing::{error, info_span, Instrument};
+use reqwest::{Client, Method};
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::metrics::observe_supabase_call;
+
+// Rows requested per page by `Query::execute_all`
+const PAGE_SIZE: usize = 1000;
+
+// Supabase config
+#[derive(Clone)]
+pub struct SupabaseConfig {
+    pub url: String,
+    pub anon_key: String,
+    pub admin_key: String, // For admin operations
+}
+
+// Which key a request is made with: anon (RLS as anonymous), admin (service role, bypasses RLS)
+// or the caller's own JWT so row-level security applies to them
+#[derive(Clone, Debug)]
+pub enum Auth {
+    Anon,
+    Admin,
+    User(String),
+}
+
+#[derive(Debug)]
+pub enum SupabaseError {
+    Request(reqwest::Error),
+    Status { status: u16, body: String },
+    Decode(String),
+}
+
+impl fmt::Display for SupabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SupabaseError::Request(e) => write!(f, "Supabase request failed: {}", e),
+            SupabaseError::Status { status, body } => write!(f, "Supabase returned {}: {}", status, body),
+            SupabaseError::Decode(e) => write!(f, "Supabase response could not be decoded: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SupabaseError {}
+
+// Rows plus the total from Content-Range when `count_exact` was requested
+#[derive(Debug, Default)]
+pub struct QueryResponse {
+    pub data: serde_json::Value,
+    pub count: Option<u64>,
+}
+
+impl QueryResponse {
+    pub fn rows(&self) -> &[serde_json::Value] {
+        self.data.as_array().map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn first(&self) -> Option<&serde_json::Value> {
+        self.rows().first()
+    }
+}
+
+// PostgREST request builder; every handler goes through this instead of hand-building URLs
+#[derive(Clone, Debug)]
+pub struct Query {
+    path: String,
+    method: Method,
+    params: Vec<(String, String)>,
+    prefer: Vec<&'static str>,
+    body: Option<serde_json::Value>,
+    auth: Auth,
+}
+
+impl Query {
+    pub fn table(table: &str) -> Query {
+        Query {
+            path: table.to_string(),
+            method: Method::GET,
+            params: Vec::new(),
+            prefer: Vec::new(),
+            body: None,
+            auth: Auth::Anon,
+        }
+    }
+
+    pub fn rpc(function: &str, args: serde_json::Value) -> Query {
+        Query {
+            path: format!("rpc/{}", function),
+            method: Method::POST,
+            body: Some(args),
+            ..Query::table("")
+        }
+    }
+
+    pub fn auth(mut self, auth: Auth) -> Query {
+        self.auth = auth;
+        self
+    }
+
+    pub fn admin(self) -> Query {
+        self.auth(Auth::Admin)
+    }
+
+    // Select list, including embedded resources e.g. "id, achievements(name, tier)"
+    pub fn select(mut self, columns: &str) -> Query {
+        let columns: String = columns.chars().filter(|c| !c.is_whitespace()).collect();
+        self.params.push(("select".to_string(), columns));
+        self
+    }
+
+    fn filter(mut self, column: &str, op: &str, value: impl fmt::Display) -> Query {
+        self.params.push((column.to_string(), format!("{}.{}", op, value)));
+        self
+    }
+
+    pub fn eq(self, column: &str, value: impl fmt::Display) -> Query {
+        self.filter(column, "eq", value)
+    }
+
+    pub fn neq(self, column: &str, value: impl fmt::Display) -> Query {
+        self.filter(column, "neq", value)
+    }
+
+    pub fn gte(self, column: &str, value: impl fmt::Display) -> Query {
+        self.filter(column, "gte", value)
+    }
+
+    pub fn lte(self, column: &str, value: impl fmt::Display) -> Query {
+        self.filter(column, "lte", value)
+    }
+
+    pub fn in_<T: fmt::Display>(self, column: &str, values: impl IntoIterator<Item = T>) -> Query {
+        let list: Vec<String> = values.into_iter().map(|v| v.to_string()).collect();
+        self.filter(column, "in", format!("({})", list.join(",")))
+    }
+
+    // `is` filter: value is one of null, true, false
+    pub fn is(self, column: &str, value: &str) -> Query {
+        self.filter(column, "is", value)
+    }
+
+    // Raw PostgREST disjunction, e.g. "unit_preference.is.null,unit_preference.eq.metric"
+    pub fn or(mut self, expression: &str) -> Query {
+        self.params.push(("or".to_string(), format!("({})", expression)));
+        self
+    }
+
+    // Repeated calls add tie-breakers: order=a.desc,b.asc
+    pub fn order(mut self, column: &str, descending: bool) -> Query {
+        let direction = if descending { "desc" } else { "asc" };
+        let term = format!("{}.{}", column, direction);
+        match self.params.iter_mut().find(|(key, _)| key == "order") {
+            Some((_, order)) => {
+                order.push(',');
+                order.push_str(&term);
+            }
+            None => self.params.push(("order".to_string(), term)),
+        }
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Query {
+        self.params.push(("limit".to_string(), limit.to_string()));
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Query {
+        self.params.push(("offset".to_string(), offset.to_string()));
+        self
+    }
+
+    pub fn count_exact(mut self) -> Query {
+        self.prefer.push("count=exact");
+        self
+    }
+
+    pub fn insert(mut self, body: serde_json::Value) -> Query {
+        self.method = Method::POST;
+        self.body = Some(body);
+        self.prefer.push("return=representation");
+        self
+    }
+
+    // Insert-or-update on the table's primary key, or on `on_conflict` columns if given
+    pub fn upsert(mut self, body: serde_json::Value) -> Query {
+        self.method = Method::POST;
+        self.body = Some(body);
+        self.prefer.push("return=representation");
+        self.prefer.push("resolution=merge-duplicates");
+        self
+    }
+
+    // Insert, skipping rows that collide on the `on_conflict` columns; only rows actually
+    // inserted come back, so callers can tell a new row from one that already existed
+    pub fn insert_ignoring_duplicates(mut self, body: serde_json::Value) -> Query {
+        self.method = Method::POST;
+        self.body = Some(body);
+        self.prefer.push("return=representation");
+        self.prefer.push("resolution=ignore-duplicates");
+        self
+    }
+
+    pub fn on_conflict(mut self, columns: &str) -> Query {
+        self.params.push(("on_conflict".to_string(), columns.to_string()));
+        self
+    }
+
+    pub fn update(mut self, body: serde_json::Value) -> Query {
+        self.method = Method::PATCH;
+        self.body = Some(body);
+        self.prefer.push("return=representation");
+        self
+    }
+
+    pub fn delete(mut self) -> Query {
+        self.method = Method::DELETE;
+        self.prefer.push("return=representation");
+        self
+    }
+
+    pub async fn execute(self, config: &SupabaseConfig) -> Result<QueryResponse, SupabaseError> {
+        execute_supabase_query(config, self).await
+    }
+
+    // PostgREST caps rows per response, so larger reads are paged; the query needs a stable order
+    pub async fn execute_all(self, config: &SupabaseConfig) -> Result<Vec<serde_json::Value>, SupabaseError> {
+        let mut rows = Vec::new();
+        loop {
+            let response = self.clone().limit(PAGE_SIZE).offset(rows.len()).execute(config).await?;
+            let page = response.rows();
+            rows.extend_from_slice(page);
+            if page.len() < PAGE_SIZE {
+                return Ok(rows);
+            }
+        }
+    }
+}
+
+// One pooled HTTP client for all Supabase calls
+pub fn http_client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(Client::new)
+}
+
+// "0-24/3573" or "*/3573" -> 3573
+fn parse_content_range(header: &str) -> Option<u64> {
+    header.rsplit('/').next()?.parse().ok()
+}
+
+// Execute Supabase query (deduplicated for all GET/POST/PATCH/DELETE/RPC)
+pub async fn execute_supabase_query(config: &SupabaseConfig, query: Query) -> Result<QueryResponse, SupabaseError> {
+    let (target, method) = (query.path.clone(), query.method.to_string());
+    let span = info_span!("supabase", target = %target, method = %method);
+    let started = Instant::now();
+    let result = send_query(config, query).instrument(span).await;
+    let error_kind = result.as_ref().err().map(|e| match e {
+        SupabaseError::Request(_) => "request".to_string(),
+        SupabaseError::Status { status, .. } => status.to_string(),
+        SupabaseError::Decode(_) => "decode".to_string(),
+    });
+    observe_supabase_call(&target, &method, started.elapsed(), error_kind.as_deref());
+    result
+}
+
+async fn send_query(config: &SupabaseConfig, query: Query) -> Result<QueryResponse, SupabaseError> {
+    let url = format!("{}/rest/v1/{}", config.url, query.path);
+    let (apikey, bearer) = match &query.auth {
+        Auth::Anon => (&config.anon_key, config.anon_key.as_str()),
+        Auth::Admin => (&config.admin_key, config.admin_key.as_str()),
+        Auth::User(token) => (&config.anon_key, token.as_str()),
+    };
+
+    let mut request = http_client()
+        .request(query.method.clone(), &url)
+        .header("apikey", apikey)
+        .bearer_auth(bearer)
+        .query(&query.params);
+    if !query.prefer.is_empty() {
+        request = request.header("Prefer", query.prefer.join(","));
+    }
+    if let Some(body) = &query.body {
+        request = request.json(body);
+    }
+
+    let resp = request.send().await.map_err(|e| {
+        error!("Supabase {} {} error: {}", query.method, query.path, e);
+        SupabaseError::Request(e)
+    })?;
+    let status = resp.status();
+    let count = resp
+        .headers()
+        .get("content-range")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range);
+    let text = resp.text().await.map_err(SupabaseError::Request)?;
+
+    if !status.is_success() {
+        error!("Supabase {} {} failed with {}: {}", query.method, query.path, status, text);
+        return Err(SupabaseError::Status { status: status.as_u16(), body: text });
+    }
+
+    let data = if text.trim().is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_str(&text).map_err(|e| {
+            error!("JSON parse error for {} {}: {}", query.method, query.path, e);
+            SupabaseError::Decode(e.to_string())
+        })?
+    };
+    Ok(QueryResponse { data, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The query string reqwest sends for this builder
+    fn query_string(query: &Query) -> String {
+        Client::new()
+            .get("http://localhost/rest/v1/ruck_session")
+            .query(&query.params)
+            .build()
+            .unwrap()
+            .url()
+            .query()
+            .unwrap_or("")
+            .to_string()
+    }
+
+    #[test]
+    fn range_filters_use_postgrest_operators() {
+        let query = Query::table("ruck_session").select("id").gte("distance_km", 5).lte("distance_km", 10.5).neq("status", "deleted");
+        assert_eq!(query_string(&query), "select=id&distance_km=gte.5&distance_km=lte.10.5&status=neq.deleted");
+    }
+
+    #[test]
+    fn repeated_order_adds_tie_breakers() {
+        let query = Query::table("ruck_session").order("started_at", true).order("id", false).limit(1);
+        assert_eq!(query_string(&query), "order=started_at.desc%2Cid.asc&limit=1");
+    }
+}
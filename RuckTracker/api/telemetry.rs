@@ -0,0 +1,88 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::error::InternalError;
+use actix_web::Error as ActixError;
+use std::time::Instant;
+use tracing::{error, field, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Incoming ids longer than this, or with anything but visible ASCII, are replaced with a fresh one
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// JSON lines on stdout, one object per event carrying the fields of every enclosing span.
+// RUST_LOG filters as before (default info); `log` records from dependencies are forwarded too.
+pub fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(filter)
+        .with_current_span(false)
+        .with_span_list(true)
+        .init();
+}
+
+fn incoming_request_id(req: &ServiceRequest) -> Option<String> {
+    let raw = req.headers().get(&REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !raw.is_empty() && raw.len() <= MAX_REQUEST_ID_LEN && raw.bytes().all(|b| b.is_ascii_graphic());
+    valid.then(|| raw.to_string())
+}
+
+// Use the caller's X-Request-ID or generate one, run the request inside a span carrying it, echo
+// it on the response and log one line per request with status and latency
+pub async fn request_tracing(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixError> {
+    let request_id = incoming_request_id(&req).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    // user_id is filled in by jwt_auth once the bearer token is verified
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        route = %route,
+        path = %req.path(),
+        user_id = field::Empty,
+    );
+    let header = HeaderValue::from_str(&request_id).ok();
+    let started = Instant::now();
+
+    let result = next.call(req).instrument(span.clone()).await;
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+    span.in_scope(|| {
+        if status.is_server_error() {
+            error!(status = status.as_u16(), elapsed_ms, "request failed");
+        } else if status.is_client_error() {
+            warn!(status = status.as_u16(), elapsed_ms, "request rejected");
+        } else {
+            info!(status = status.as_u16(), elapsed_ms, "request completed");
+        }
+    });
+
+    match result {
+        Ok(mut res) => {
+            if let Some(header) = header {
+                res.headers_mut().insert(REQUEST_ID_HEADER, header);
+            }
+            Ok(res)
+        }
+        // Errors from inner middleware (e.g. a rejected bearer token) are rendered later, so the
+        // header goes on the response they will render as
+        Err(e) => {
+            let mut response = e.error_response();
+            if let Some(header) = header {
+                response.headers_mut().insert(REQUEST_ID_HEADER, header);
+            }
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
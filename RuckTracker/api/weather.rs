@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// Thresholds for the temperature and wind buckets (metric, as weather.py requests from OpenWeatherMap)
+pub const HEAT_MIN_C: f64 = 30.0;
+pub const COLD_MAX_C: f64 = 0.0;
+pub const WIND_MIN_MS: f64 = 10.0;
+
+// Canonical conditions a session's weather is sorted into; one session can land in several,
+// e.g. a windy snowstorm is snow, cold and wind
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherBucket {
+    Rain,
+    Snow,
+    Heat,
+    Cold,
+    Wind,
+    Clear,
+}
+
+// The parts of ruck_session.weather_conditions we classify on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeatherSnapshot {
+    pub temperature_c: Option<f64>,
+    pub wind_speed_ms: Option<f64>,
+    pub condition_code: Option<i64>,
+    pub condition: Option<String>,
+}
+
+fn number(value: &serde_json::Value, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|key| match value.get(*key)? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+// Reads the snapshot written from weather.py's currentWeather (camelCase) or snake_case keys.
+// A JSON string holding that object, or a bare description like "Light Rain", also works.
+pub fn parse_weather(value: &serde_json::Value) -> Option<WeatherSnapshot> {
+    match value {
+        serde_json::Value::Object(_) => {
+            let snapshot = WeatherSnapshot {
+                temperature_c: number(value, &["temperature", "temperature_c", "temp"]),
+                wind_speed_ms: number(value, &["windSpeed", "wind_speed", "wind_speed_ms"]),
+                condition_code: number(value, &["conditionCode", "condition_code"]).map(|code| code as i64),
+                condition: ["condition", "description"]
+                    .iter()
+                    .find_map(|key| value.get(*key)?.as_str())
+                    .map(str::to_string),
+            };
+            (snapshot != WeatherSnapshot::default()).then_some(snapshot)
+        }
+        serde_json::Value::String(raw) if raw.trim_start().starts_with('{') => {
+            parse_weather(&serde_json::from_str(raw).ok()?)
+        }
+        serde_json::Value::String(raw) if !raw.trim().is_empty() => {
+            Some(WeatherSnapshot { condition: Some(raw.clone()), ..WeatherSnapshot::default() })
+        }
+        _ => None,
+    }
+}
+
+// OpenWeatherMap condition codes: 2xx thunderstorm, 3xx drizzle, 5xx rain, 6xx snow, 800 clear,
+// 801 few clouds. Without a code the description text is used.
+pub fn classify(snapshot: &WeatherSnapshot) -> BTreeSet<WeatherBucket> {
+    let mut buckets = BTreeSet::new();
+    let text = snapshot.condition.as_deref().unwrap_or("").to_lowercase();
+    match snapshot.condition_code {
+        Some(200..=599) => {
+            buckets.insert(WeatherBucket::Rain);
+        }
+        Some(600..=699) => {
+            buckets.insert(WeatherBucket::Snow);
+        }
+        Some(800..=801) => {
+            buckets.insert(WeatherBucket::Clear);
+        }
+        Some(_) => {}
+        None => {
+            if ["rain", "drizzle", "shower", "thunder"].iter().any(|word| text.contains(word)) {
+                buckets.insert(WeatherBucket::Rain);
+            }
+            if ["snow", "sleet", "blizzard"].iter().any(|word| text.contains(word)) {
+                buckets.insert(WeatherBucket::Snow);
+            }
+            if ["clear", "sunny"].iter().any(|word| text.contains(word)) {
+                buckets.insert(WeatherBucket::Clear);
+            }
+        }
+    }
+    if let Some(temperature) = snapshot.temperature_c {
+        if temperature >= HEAT_MIN_C {
+            buckets.insert(WeatherBucket::Heat);
+        }
+        if temperature <= COLD_MAX_C {
+            buckets.insert(WeatherBucket::Cold);
+        }
+    }
+    if snapshot.wind_speed_ms.is_some_and(|wind| wind >= WIND_MIN_MS) {
+        buckets.insert(WeatherBucket::Wind);
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn buckets(value: serde_json::Value) -> Vec<WeatherBucket> {
+        classify(&parse_weather(&value).unwrap()).into_iter().collect()
+    }
+
+    #[test]
+    fn condition_codes_pick_the_precipitation_bucket() {
+        assert_eq!(buckets(json!({ "conditionCode": 211, "temperature": 18 })), [WeatherBucket::Rain]);
+        assert_eq!(buckets(json!({ "conditionCode": 601, "temperature": 1 })), [WeatherBucket::Snow]);
+        assert_eq!(buckets(json!({ "conditionCode": 800, "temperature": 20 })), [WeatherBucket::Clear]);
+        assert_eq!(buckets(json!({ "conditionCode": 741, "temperature": 10 })), []);
+    }
+
+    #[test]
+    fn temperature_and_wind_add_buckets_at_their_thresholds() {
+        assert_eq!(
+            buckets(json!({ "condition_code": 601, "temperature_c": 0, "wind_speed": 10 })),
+            [WeatherBucket::Snow, WeatherBucket::Cold, WeatherBucket::Wind]
+        );
+        assert_eq!(buckets(json!({ "conditionCode": 800, "temperature": 30 })), [WeatherBucket::Heat, WeatherBucket::Clear]);
+        assert_eq!(buckets(json!({ "temperature": 29.9, "windSpeed": 9.9 })), []);
+    }
+
+    #[test]
+    fn descriptions_classify_without_a_code() {
+        assert_eq!(buckets(json!("Light Rain")), [WeatherBucket::Rain]);
+        assert_eq!(buckets(json!({ "description": "Sleet showers" })), [WeatherBucket::Rain, WeatherBucket::Snow]);
+        assert_eq!(buckets(json!("{\"condition\": \"Sunny\", \"temperature\": \"31\"}")), [WeatherBucket::Heat, WeatherBucket::Clear]);
+    }
+
+    #[test]
+    fn empty_weather_is_not_a_snapshot() {
+        assert_eq!(parse_weather(&json!({})), None);
+        assert_eq!(parse_weather(&json!("  ")), None);
+        assert_eq!(parse_weather(&serde_json::Value::Null), None);
+    }
+}
@@ -0,0 +1,19 @@
+-- Add user timezone so streaks and time-of-day achievements use local calendar days
+ALTER TABLE "user"
+ADD COLUMN IF NOT EXISTS timezone VARCHAR(50);
+
+-- Seed from the most recent coaching plan timezone where we have one
+UPDATE "user" u
+SET timezone = p.plan_notification_timezone
+FROM (
+    SELECT DISTINCT ON (user_id) user_id, plan_notification_timezone
+    FROM user_coaching_plans
+    WHERE plan_notification_timezone IS NOT NULL
+      AND plan_notification_timezone <> 'UTC'
+    ORDER BY user_id, created_at DESC
+) p
+WHERE u.id = p.user_id
+  AND u.timezone IS NULL;
+
+-- Add comment
+COMMENT ON COLUMN "user".timezone IS 'IANA timezone identifier used for local-day achievement calculations (e.g., America/Denver); NULL means UTC';
@@ -0,0 +1,6 @@
+-- Add metadata to achievement_progress for computed progress details (e.g., percentage)
+ALTER TABLE achievement_progress
+ADD COLUMN IF NOT EXISTS metadata JSONB;
+
+-- Add comment
+COMMENT ON COLUMN achievement_progress.metadata IS 'Details written with each progress update, e.g. {"percentage": 73.4}';
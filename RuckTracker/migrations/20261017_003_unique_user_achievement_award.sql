@@ -0,0 +1,23 @@
+-- Make awards idempotent: a user can hold each achievement at most once
+-- Remove duplicates left by concurrent checks, keeping the earliest award (rows with no
+-- earned_at sort last, ties go to the lowest id)
+DELETE FROM user_achievements
+WHERE id IN (
+    SELECT id
+    FROM (
+        SELECT id,
+               ROW_NUMBER() OVER (
+                   PARTITION BY user_id, achievement_id
+                   ORDER BY earned_at NULLS LAST, id
+               ) AS award_rank
+        FROM user_achievements
+    ) ranked
+    WHERE award_rank > 1
+);
+
+-- Award inserts use ON CONFLICT (user_id, achievement_id) DO NOTHING against this constraint
+ALTER TABLE user_achievements
+DROP CONSTRAINT IF EXISTS user_achievements_user_id_achievement_id_key;
+
+ALTER TABLE user_achievements
+ADD CONSTRAINT user_achievements_user_id_achievement_id_key UNIQUE (user_id, achievement_id);
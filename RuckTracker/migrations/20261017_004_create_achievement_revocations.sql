@@ -0,0 +1,16 @@
+-- Audit trail for awards taken back after the session data they relied on was edited or deleted
+CREATE TABLE IF NOT EXISTS achievement_revocations (
+    id BIGSERIAL PRIMARY KEY,
+    user_achievement_id BIGINT NOT NULL,
+    user_id UUID NOT NULL,
+    achievement_id INTEGER NOT NULL REFERENCES achievements(id),
+    session_id BIGINT,
+    earned_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    metadata JSONB
+);
+
+CREATE INDEX IF NOT EXISTS idx_achievement_revocations_user_id ON achievement_revocations(user_id);
+
+-- Add comment
+COMMENT ON COLUMN achievement_revocations.metadata IS 'Original award metadata plus revocation_reason and revoked_after_session {session_id, event}';
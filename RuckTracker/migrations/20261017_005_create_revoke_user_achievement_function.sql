@@ -0,0 +1,27 @@
+-- Move one award to achievement_revocations in a single statement, so an award is never deleted
+-- without its audit row (or recorded as revoked while still held). Returns false when the award
+-- was already gone, e.g. revoked by a concurrent re-evaluation.
+CREATE OR REPLACE FUNCTION revoke_user_achievement(p_user_achievement_id BIGINT, p_metadata JSONB)
+RETURNS BOOLEAN
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  revoked_count INTEGER;
+BEGIN
+  WITH removed AS (
+    DELETE FROM user_achievements
+    WHERE id = p_user_achievement_id
+    RETURNING id, user_id, achievement_id, session_id, earned_at
+  )
+  INSERT INTO achievement_revocations (user_achievement_id, user_id, achievement_id, session_id, earned_at, revoked_at, metadata)
+  SELECT id, user_id, achievement_id, session_id, earned_at, NOW(), p_metadata
+  FROM removed;
+
+  GET DIAGNOSTICS revoked_count = ROW_COUNT;
+  RETURN revoked_count > 0;
+END;
+$$;
+
+-- Only the achievements service (service role) revokes awards
+REVOKE EXECUTE ON FUNCTION revoke_user_achievement(BIGINT, JSONB) FROM PUBLIC, anon, authenticated;
+GRANT EXECUTE ON FUNCTION revoke_user_achievement(BIGINT, JSONB) TO service_role;